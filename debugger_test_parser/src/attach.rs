@@ -0,0 +1,376 @@
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The interval used when polling for the debugger to attach or detach.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The time to wait on platforms where the presence of a debugger can not be
+/// detected or where the debugger may not be in control of the process yet.
+const FALLBACK_WAIT: Duration = Duration::from_secs(3);
+
+/// Check if a debugger is attached to the current thread by reading the
+/// `TracerPid` field of `/proc/thread-self/status`.
+#[cfg(target_os = "linux")]
+fn is_debugger_present() -> Option<bool> {
+    let status = std::fs::read_to_string("/proc/thread-self/status").ok()?;
+    let tracer_pid = status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))?;
+    Some(tracer_pid.trim() != "0")
+}
+
+/// Check if a debugger is attached to the current process using the IsDebuggerPresent API.
+/// https://docs.microsoft.com/en-us/windows/win32/api/debugapi/nf-debugapi-isdebuggerpresent
+#[cfg(windows)]
+fn is_debugger_present() -> Option<bool> {
+    extern "system" {
+        fn IsDebuggerPresent() -> i32;
+    }
+
+    Some(unsafe { IsDebuggerPresent() } != 0)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn is_debugger_present() -> Option<bool> {
+    None
+}
+
+/// The environment variable with the process id of the debugger which attaches to a test executable launched by the test.
+pub(crate) const PTRACER_ENV_VAR: &str = "DEBUGGER_TEST_PTRACER";
+
+/// The Yama ptrace scope, see https://www.kernel.org/doc/html/latest/admin-guide/LSM/Yama.html
+#[cfg(target_os = "linux")]
+const PTRACE_SCOPE_PATH: &str = "/proc/sys/kernel/yama/ptrace_scope";
+
+/// The `prctl` option to declare which process is allowed to ptrace the current process.
+#[cfg(target_os = "linux")]
+const PR_SET_PTRACER: std::os::raw::c_int = 0x59616d61;
+
+/// The `PR_SET_PTRACER` value which allows any process to ptrace the current process.
+const PR_SET_PTRACER_ANY: std::os::raw::c_ulong = std::os::raw::c_ulong::MAX;
+
+/// Set while a test of the current process has a debugger attached to it.
+static DEBUGGER_ATTACHED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn prctl(option: std::os::raw::c_int, ...) -> std::os::raw::c_int;
+}
+
+/// Get an error message if the given Yama ptrace scope prevents a debugger
+/// launched by the test from attaching to the test process.
+fn ptrace_scope_error(ptrace_scope: u32, is_root: bool) -> Option<String> {
+    match ptrace_scope {
+        2 if !is_root => Some(String::from(
+            "kernel.yama.ptrace_scope is 2, only processes with CAP_SYS_PTRACE can attach a debugger to the test process.\n\
+            Run the tests with CAP_SYS_PTRACE or lower the scope with `sudo sysctl kernel.yama.ptrace_scope=1`.",
+        )),
+        3 => Some(String::from(
+            "kernel.yama.ptrace_scope is 3, attaching a debugger to the test process is disabled until the next reboot.",
+        )),
+        _ => None,
+    }
+}
+
+/// Releases the current process for the debugger of another test when dropped.
+pub struct DebuggerAttachGuard {
+    _private: (),
+}
+
+impl DebuggerAttachGuard {
+    /// Declare the launched debugger as the only process which is allowed to attach to the current process.
+    pub fn declare_debugger(&self, debugger: &Child) -> anyhow::Result<()> {
+        allow_ptracer(debugger.id() as std::os::raw::c_ulong)
+    }
+}
+
+impl Drop for DebuggerAttachGuard {
+    fn drop(&mut self) {
+        // No other process is allowed to attach to the current process once the test is done.
+        let _ = allow_ptracer(0);
+        DEBUGGER_ATTACHED.store(false, Ordering::Release);
+    }
+}
+
+/// Wait until no other test has a debugger attached to the current process, then allow the debugger
+/// launched next to attach to it. A process can only be traced by one debugger at a time, so the tests
+/// which attach a debugger run one after another until the returned guard is dropped.
+///
+/// With `kernel.yama.ptrace_scope = 1`, only ancestors of a process can attach to it. Any process is
+/// allowed to attach until the launched debugger is declared with `DebuggerAttachGuard::declare_debugger`,
+/// so the debugger can attach as soon as it starts. Dropping the guard disallows attaching again.
+pub fn allow_debugger_attach() -> anyhow::Result<DebuggerAttachGuard> {
+    let guard = lock_debugger_attach();
+    allow_ptracer(PR_SET_PTRACER_ANY)?;
+    Ok(guard)
+}
+
+/// Wait until no other test has a debugger attached to the current process.
+fn lock_debugger_attach() -> DebuggerAttachGuard {
+    while DEBUGGER_ATTACHED
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    DebuggerAttachGuard { _private: () }
+}
+
+/// Allow the given ptracer, a process id or `PR_SET_PTRACER_ANY`, to attach to the current process.
+#[cfg(target_os = "linux")]
+fn allow_ptracer(ptracer: std::os::raw::c_ulong) -> anyhow::Result<()> {
+    let ptrace_scope = match std::fs::read_to_string(PTRACE_SCOPE_PATH) {
+        Ok(ptrace_scope) => ptrace_scope.trim().parse::<u32>()?,
+        // Yama is not enabled, there are no additional ptrace restrictions.
+        Err(_) => return Ok(()),
+    };
+
+    let status = std::fs::read_to_string("/proc/self/status")?;
+    let is_root = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|uids| uids.split_whitespace().nth(1))
+        == Some("0");
+    if let Some(error) = ptrace_scope_error(ptrace_scope, is_root) {
+        anyhow::bail!(error);
+    }
+
+    if ptrace_scope == 1
+        && unsafe { prctl(PR_SET_PTRACER, ptracer, 0 as std::os::raw::c_ulong) } != 0
+    {
+        anyhow::bail!(
+            "Failed to allow the debugger to attach to the test process: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allow_ptracer(_ptracer: std::os::raw::c_ulong) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Wait for a debugger to attach to the test executable when it was launched by a test which attaches
+/// a debugger to it, i.e. through a debug adapter. Does nothing when the test executable was not launched
+/// for a debugger to attach to it.
+pub fn wait_for_debugger(timeout: Duration) -> anyhow::Result<()> {
+    let ptracer_pid = match std::env::var(PTRACER_ENV_VAR) {
+        Ok(ptracer_pid) => ptracer_pid.parse::<u32>()?,
+        Err(_) => return Ok(()),
+    };
+    allow_ptracer(ptracer_pid as std::os::raw::c_ulong)?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match is_debugger_present() {
+            Some(true) => return Ok(()),
+            Some(false) => {}
+            None => {
+                // The presence of a debugger can not be detected, wait and assume it has attached.
+                thread::sleep(FALLBACK_WAIT);
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "Debugger did not attach to the test executable within {} seconds.",
+                timeout.as_secs()
+            );
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Wait for the debugger to attach to the current process.
+/// Fails if the debugger exits or has not attached before the timeout.
+pub fn wait_for_debugger_attach(debugger: &mut Child, timeout: Duration) -> anyhow::Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = debugger.try_wait()? {
+            anyhow::bail!(
+                "Debugger exited with {} before attaching to the test process.",
+                status
+            );
+        }
+
+        match is_debugger_present() {
+            Some(true) => break,
+            Some(false) => {}
+            None => {
+                // The presence of a debugger can not be detected, wait and assume it has attached.
+                thread::sleep(FALLBACK_WAIT);
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "Debugger did not attach to the test process within {} seconds.",
+                timeout.as_secs()
+            );
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // On Windows, the debugger is attached before it breaks into the process,
+    // wait to ensure the debugger is in control of the process.
+    if cfg!(windows) {
+        thread::sleep(FALLBACK_WAIT);
+    }
+
+    Ok(())
+}
+
+/// Wait for the debugger to detach from the current process and exit.
+/// Returns `None` if the debugger is still running after the timeout.
+pub fn wait_for_debugger_exit(
+    debugger: &mut Child,
+    timeout: Duration,
+) -> anyhow::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = debugger.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        match is_debugger_present() {
+            Some(true) => {}
+            Some(false) => break,
+            None => {
+                // The presence of a debugger can not be detected, wait and assume it has detached.
+                thread::sleep(FALLBACK_WAIT);
+                break;
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // The debugger has detached, wait for the debugger process to exit.
+    loop {
+        if let Some(status) = debugger.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_is_debugger_present() {
+    assert_eq!(Some(false), is_debugger_present());
+}
+
+#[test]
+fn test_ptrace_scope_error() {
+    assert!(ptrace_scope_error(0, false).is_none());
+    assert!(ptrace_scope_error(1, false).is_none());
+    assert!(ptrace_scope_error(2, true).is_none());
+    assert!(ptrace_scope_error(2, false)
+        .unwrap()
+        .starts_with("kernel.yama.ptrace_scope is 2"));
+    assert!(ptrace_scope_error(3, true)
+        .unwrap()
+        .starts_with("kernel.yama.ptrace_scope is 3"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_allow_debugger_attach() {
+    let result = allow_debugger_attach();
+
+    let ptrace_scope = std::fs::read_to_string(PTRACE_SCOPE_PATH)
+        .map(|ptrace_scope| ptrace_scope.trim().to_string())
+        .unwrap_or_default();
+    if ptrace_scope != "2" && ptrace_scope != "3" {
+        result.unwrap();
+    }
+}
+
+#[test]
+fn test_allow_debugger_attach_parallel() {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    // Each thread attaches a debugger in turn, so at most one holds the guard at a time.
+    let attached = Arc::new(AtomicUsize::new(0));
+    let threads = (0..4)
+        .map(|_| {
+            let attached = attached.clone();
+            thread::spawn(move || {
+                for _ in 0..5 {
+                    let guard = lock_debugger_attach();
+                    assert_eq!(0, attached.fetch_add(1, Ordering::SeqCst));
+                    thread::sleep(Duration::from_millis(1));
+                    attached.fetch_sub(1, Ordering::SeqCst);
+                    drop(guard);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_wait_for_debugger_attach_exited() {
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let result = wait_for_debugger_attach(&mut child, Duration::from_secs(5));
+    assert_eq!(
+        "Debugger exited with exit status: 0 before attaching to the test process.",
+        format!("{}", result.unwrap_err())
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_wait_for_debugger_attach_timeout() {
+    let mut child = std::process::Command::new("sleep")
+        .arg("5")
+        .spawn()
+        .unwrap();
+    let result = wait_for_debugger_attach(&mut child, Duration::from_secs(0));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(
+        "Debugger did not attach to the test process within 0 seconds.",
+        format!("{}", result.unwrap_err())
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_wait_for_debugger_exit() {
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let status = wait_for_debugger_exit(&mut child, Duration::from_secs(5)).unwrap();
+    assert!(status.unwrap().success());
+
+    let mut child = std::process::Command::new("sleep")
+        .arg("5")
+        .spawn()
+        .unwrap();
+    let status = wait_for_debugger_exit(&mut child, Duration::from_millis(200)).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(status.is_none());
+}
@@ -0,0 +1,101 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The location of a breakpoint set in addition to the `__break` function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BreakpointLocation {
+    /// A line of a source file, i.e. `src/foo.rs:42`.
+    Line { file: String, line: u32 },
+    /// A function, i.e. `my_crate::parse`.
+    Function(String),
+}
+
+impl Display for BreakpointLocation {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            BreakpointLocation::Line { file, line } => write!(fmt, "{}:{}", file, line),
+            BreakpointLocation::Function(function) => write!(fmt, "{}", function),
+        }
+    }
+}
+
+impl FromStr for BreakpointLocation {
+    type Err = anyhow::Error;
+
+    /// Attempts to parse a string into a BreakpointLocation
+    /// A location ending with `:<line>` is a line of a source file, any other location is a function.
+    fn from_str(s: &str) -> Result<BreakpointLocation, Self::Err> {
+        let location = s.trim();
+        if location.is_empty() {
+            anyhow::bail!("Invalid breakpoint location: `{}`.", s);
+        }
+
+        if let Some(index) = location.rfind(':') {
+            let (file, line) = (&location[..index], &location[index + 1..]);
+            if !file.is_empty() && !file.ends_with(':') {
+                if let Ok(line) = line.parse::<u32>() {
+                    return Ok(BreakpointLocation::Line {
+                        file: file.to_string(),
+                        line,
+                    });
+                }
+            }
+        }
+
+        Ok(BreakpointLocation::Function(location.to_string()))
+    }
+}
+
+/// The prefix of the function called by `break_here!` for a breakpoint label, i.e. `__break_here_after_push`.
+pub const BREAK_LABEL_FUNCTION_PREFIX: &str = "__break_here_";
+
+/// Get the name of the function called by `break_here!` for a breakpoint label.
+/// The label is part of the function name, so it can only contain letters, digits and underscores.
+pub fn break_label_function(label: &str) -> anyhow::Result<String> {
+    if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        anyhow::bail!(
+            "Invalid breakpoint label: `{}`, expected only letters, digits and underscores.",
+            label
+        );
+    }
+
+    Ok(format!("{}{}", BREAK_LABEL_FUNCTION_PREFIX, label))
+}
+
+#[test]
+fn test_breakpoint_location_from_str() {
+    assert_eq!(
+        BreakpointLocation::Line {
+            file: "src/foo.rs".to_string(),
+            line: 42
+        },
+        BreakpointLocation::from_str(" src/foo.rs:42 ").unwrap()
+    );
+    assert_eq!(
+        BreakpointLocation::Line {
+            file: "C:\\src\\foo.rs".to_string(),
+            line: 7
+        },
+        BreakpointLocation::from_str("C:\\src\\foo.rs:7").unwrap()
+    );
+    assert_eq!(
+        BreakpointLocation::Function("my_crate::parse".to_string()),
+        BreakpointLocation::from_str("my_crate::parse").unwrap()
+    );
+    assert_eq!(
+        "Invalid breakpoint location: ``.",
+        BreakpointLocation::from_str("").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_break_label_function() {
+    assert_eq!(
+        "__break_here_after_push",
+        break_label_function("after_push").unwrap()
+    );
+    assert_eq!(
+        "Invalid breakpoint label: `after push`, expected only letters, digits and underscores.",
+        break_label_function("after push").unwrap_err().to_string()
+    );
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use regex::{Captures as RegexCaptures, Regex};
+
+use crate::PATTERN_PREFIX;
+
+/// Get the `pattern:` expected statements which define named capture groups, i.e. `(?P<ptr>0x[0-9a-f]+)`.
+fn capture_patterns(expected_statements: &[&str]) -> anyhow::Result<Vec<Regex>> {
+    let mut patterns = Vec::new();
+    for expected in expected_statements.iter().map(|line| line.trim()) {
+        if let Some(pattern) = expected.strip_prefix(PATTERN_PREFIX) {
+            let re = match Regex::new(pattern) {
+                Ok(re) => re,
+                Err(error) => anyhow::bail!("Invalid regex pattern: {}\n{}", pattern, error),
+            };
+
+            if re.capture_names().any(|name| name.is_some()) {
+                patterns.push(re);
+            }
+        }
+    }
+
+    Ok(patterns)
+}
+
+/// Get the names of the capture groups defined by the `pattern:` expected statements.
+pub fn capture_names(expected_statements: &[&str]) -> anyhow::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for re in capture_patterns(expected_statements)? {
+        names.extend(re.capture_names().flatten().map(|name| name.to_string()));
+    }
+
+    Ok(names)
+}
+
+/// Get the pattern of the references to captured values in a debugger command, i.e. `${ptr}`.
+fn reference_pattern() -> Regex {
+    Regex::new(r"\$\{(\w+)\}").expect("reference pattern is valid")
+}
+
+/// Get the names of the captured values referenced by a debugger command which are defined by the given capture names.
+pub fn capture_references(debugger_command: &str, capture_names: &[String]) -> Vec<String> {
+    reference_pattern()
+        .captures_iter(debugger_command)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| capture_names.contains(name))
+        .collect()
+}
+
+/// The values captured from the debugger output by the named groups of the `pattern:` expected statements.
+pub(crate) struct Captures {
+    patterns: Vec<Regex>,
+    names: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl Captures {
+    pub(crate) fn new(expected_statements: &[&str]) -> anyhow::Result<Self> {
+        Ok(Captures {
+            patterns: capture_patterns(expected_statements)?,
+            names: capture_names(expected_statements)?,
+            values: HashMap::new(),
+        })
+    }
+
+    /// Capture the values from the output of a debugger command.
+    /// A value captured again by a later debugger command replaces the earlier value.
+    pub(crate) fn capture(&mut self, debugger_output: &str) {
+        for line in debugger_output.lines().map(|line| line.trim()) {
+            for re in self.patterns.iter() {
+                if let Some(caps) = re.captures(line) {
+                    for name in re.capture_names().flatten() {
+                        if let Some(value) = caps.name(name) {
+                            self.values
+                                .insert(name.to_string(), value.as_str().to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replace the references to captured values in a debugger command, i.e. `${ptr}`, with the captured values.
+    /// References to names which are not capture groups are left as is, i.e. cdb aliases.
+    pub(crate) fn substitute(&self, debugger_command: &str) -> anyhow::Result<String> {
+        for name in capture_references(debugger_command, &self.names) {
+            if !self.values.contains_key(&name) {
+                anyhow::bail!(
+                    "The debugger command `{}` references `${{{}}}`, which was not captured by an earlier debugger command.",
+                    debugger_command.trim(),
+                    name
+                );
+            }
+        }
+
+        let substituted =
+            reference_pattern().replace_all(debugger_command, |caps: &RegexCaptures| {
+                match self.values.get(&caps[1]) {
+                    Some(value) => value.clone(),
+                    None => caps[0].to_string(),
+                }
+            });
+        Ok(substituted.into_owned())
+    }
+}
+
+#[test]
+fn test_capture_names() {
+    let names = capture_names(&[
+        "a = 10",
+        "pattern:a = \\d+",
+        "pattern:(?P<ptr>0x[0-9a-f]+) (?P<len>\\d+)",
+    ])
+    .unwrap();
+    assert_eq!(vec!["ptr".to_string(), "len".to_string()], names);
+
+    assert_eq!(
+        vec!["ptr".to_string()],
+        capture_references("dx ${ptr} ${alias}", &names)
+    );
+}
+
+#[test]
+fn test_captures() {
+    let mut captures = Captures::new(&["pattern:ptr = (?P<ptr>0x[0-9a-f]+)"]).unwrap();
+    assert_eq!(
+        "The debugger command `x ${ptr}` references `${ptr}`, which was not captured by an earlier debugger command.",
+        captures.substitute("x ${ptr}").unwrap_err().to_string()
+    );
+
+    captures.capture("start_debugger_command_0\n  ptr = 0x1000\nend_debugger_command_0\n");
+    assert_eq!(
+        "x 0x1000 ${alias}",
+        captures.substitute("x ${ptr} ${alias}").unwrap()
+    );
+
+    captures.capture("ptr = 0x2000\n");
+    assert_eq!("x 0x2000", captures.substitute("x ${ptr}").unwrap());
+}
@@ -0,0 +1,750 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::attach::PTRACER_ENV_VAR;
+use crate::BreakpointLocation;
+
+/// The function called by tests at each place the debugger should stop.
+/// The driver steps out of it automatically, the same as the `finish` breakpoint command of the gdb script.
+const BREAK_FUNCTION: &str = "__break";
+
+/// The maximum depth of children expanded when rendering the variables of a frame.
+const MAX_VARIABLE_DEPTH: usize = 3;
+
+/// The interval used when polling for the debug adapter and the test executable to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Read a Debug Adapter Protocol message, i.e. `Content-Length: 2\r\n\r\n{}`.
+/// Returns `None` at the end of the stream.
+fn read_message<R: BufRead>(reader: &mut R) -> anyhow::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = Some(length.trim().parse::<usize>()?);
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| anyhow::anyhow!("Debug adapter message is missing `Content-Length`."))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+/// Write a Debug Adapter Protocol message.
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> anyhow::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Get a string from a JSON value, rendering other values as JSON.
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Check if a frame is the `__break` function, i.e. `test::__break` or `test::__break()`.
+fn is_break_frame(frame_name: &str) -> bool {
+    frame_name.trim_end_matches("()").ends_with(BREAK_FUNCTION)
+}
+
+/// A Debug Adapter Protocol session which attaches the debug adapter to the test executable,
+/// runs the debugger commands and collects the results of each request.
+struct DapSession<W: Write> {
+    stdin: W,
+    receiver: Receiver<Value>,
+    deadline: Instant,
+    timeout: Duration,
+    seq: i64,
+    responses: HashMap<i64, Value>,
+    stopped_events: VecDeque<Value>,
+    initialized: bool,
+    terminated: bool,
+    thread_id: Option<i64>,
+    /// Set when the user specified location breakpoints, the test stops at them without stepping out.
+    has_location_breakpoints: bool,
+    transcript: String,
+}
+
+impl<W: Write> DapSession<W> {
+    fn new(stdin: W, receiver: Receiver<Value>, timeout: Duration) -> Self {
+        DapSession {
+            stdin,
+            receiver,
+            deadline: Instant::now() + timeout,
+            timeout,
+            seq: 0,
+            responses: HashMap::new(),
+            stopped_events: VecDeque::new(),
+            initialized: false,
+            terminated: false,
+            thread_id: None,
+            has_location_breakpoints: false,
+            transcript: String::new(),
+        }
+    }
+
+    fn send_request(&mut self, command: &str, arguments: Value) -> anyhow::Result<i64> {
+        self.seq += 1;
+        let request = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        write_message(&mut self.stdin, &request)?;
+        Ok(self.seq)
+    }
+
+    /// Read the next message from the debug adapter, handling events and storing responses.
+    fn read_message(&mut self) -> anyhow::Result<()> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        let message = match self.receiver.recv_timeout(remaining) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => anyhow::bail!(
+                "The debug adapter did not respond within {} seconds.",
+                self.timeout.as_secs()
+            ),
+            Err(RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("The debug adapter exited unexpectedly.")
+            }
+        };
+
+        match message["type"].as_str() {
+            Some("response") => {
+                if let Some(request_seq) = message["request_seq"].as_i64() {
+                    self.responses.insert(request_seq, message);
+                }
+            }
+            Some("event") => match message["event"].as_str() {
+                Some("initialized") => self.initialized = true,
+                Some("stopped") => self.stopped_events.push_back(message),
+                Some("exited") | Some("terminated") => self.terminated = true,
+                Some("output") => {
+                    // Only the output of the debugger is kept, the output of the test executable is not.
+                    let category = message["body"]["category"].as_str().unwrap_or("console");
+                    if category == "console" || category == "important" {
+                        if let Some(output) = message["body"]["output"].as_str() {
+                            self.transcript.push_str(output);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the response to a request and return its body.
+    fn wait_for_response(&mut self, seq: i64) -> anyhow::Result<Value> {
+        loop {
+            if let Some(response) = self.responses.remove(&seq) {
+                if response["success"].as_bool() != Some(true) {
+                    let message = response["body"]["error"]["format"]
+                        .as_str()
+                        .or_else(|| response["message"].as_str())
+                        .unwrap_or("unknown error");
+                    anyhow::bail!(
+                        "`{}` request failed: {}",
+                        value_to_string(&response["command"]),
+                        message
+                    );
+                }
+                return Ok(response["body"].clone());
+            }
+
+            self.read_message()?;
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> anyhow::Result<Value> {
+        let seq = self.send_request(command, arguments)?;
+        self.wait_for_response(seq)
+    }
+
+    /// Wait for the test executable to stop, returns `None` if it exited.
+    fn wait_for_stop(&mut self) -> anyhow::Result<Option<Value>> {
+        loop {
+            if let Some(event) = self.stopped_events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if self.terminated {
+                return Ok(None);
+            }
+
+            self.read_message()?;
+        }
+    }
+
+    fn top_frame(&mut self, thread_id: i64) -> anyhow::Result<Value> {
+        let body = self.request(
+            "stackTrace",
+            json!({ "threadId": thread_id, "startFrame": 0, "levels": 1 }),
+        )?;
+        Ok(body["stackFrames"][0].clone())
+    }
+
+    /// Run the test executable to the next call of the `__break` function and step out of it,
+    /// or to the next user specified location breakpoint.
+    /// Returns `false` if the test executable exited.
+    fn run_to_break(&mut self) -> anyhow::Result<bool> {
+        loop {
+            let event = match self.wait_for_stop()? {
+                Some(event) => event,
+                None => {
+                    self.thread_id = None;
+                    self.transcript.push_str("exited\n");
+                    return Ok(false);
+                }
+            };
+
+            let thread_id = event["body"]["threadId"]
+                .as_i64()
+                .or(self.thread_id)
+                .ok_or_else(|| anyhow::anyhow!("The stopped event has no thread."))?;
+            self.thread_id = Some(thread_id);
+
+            let frame = self.top_frame(thread_id)?;
+            if !is_break_frame(frame["name"].as_str().unwrap_or_default()) {
+                let reason = event["body"]["reason"].as_str().unwrap_or_default();
+                if self.has_location_breakpoints && reason.ends_with("breakpoint") {
+                    self.render_stop(&event, &frame);
+                    return Ok(true);
+                }
+
+                // The test executable stopped before the first call of the `__break` function, i.e. when attaching.
+                self.request("continue", json!({ "threadId": thread_id }))?;
+                continue;
+            }
+
+            // Step out of the `__break` function to the frame of the test.
+            self.request("stepOut", json!({ "threadId": thread_id }))?;
+            let thread_id = match self.wait_for_stop()? {
+                Some(event) => event["body"]["threadId"].as_i64().unwrap_or(thread_id),
+                None => {
+                    self.thread_id = None;
+                    self.transcript.push_str("exited\n");
+                    return Ok(false);
+                }
+            };
+            self.thread_id = Some(thread_id);
+
+            let frame = self.top_frame(thread_id)?;
+            self.render_stop(&event, &frame);
+            return Ok(true);
+        }
+    }
+
+    fn render_stop(&mut self, event: &Value, frame: &Value) {
+        self.transcript.push_str(&format!(
+            "stopped.reason = {}\nstopped.frame.name = {}\nstopped.frame.line = {}\n",
+            value_to_string(&event["body"]["reason"]),
+            value_to_string(&frame["name"]),
+            value_to_string(&frame["line"]),
+        ));
+    }
+
+    fn current_thread(&self) -> anyhow::Result<i64> {
+        self.thread_id
+            .ok_or_else(|| anyhow::anyhow!("The test executable is not stopped."))
+    }
+
+    /// Render the variables with the given reference as lines of `name = value`.
+    fn render_variables(
+        &mut self,
+        variables_reference: i64,
+        prefix: &str,
+        depth: usize,
+    ) -> anyhow::Result<()> {
+        let body = self.request(
+            "variables",
+            json!({ "variablesReference": variables_reference }),
+        )?;
+        let variables = body["variables"].as_array().cloned().unwrap_or_default();
+        for variable in variables {
+            let name = value_to_string(&variable["name"]);
+            let path = if prefix.is_empty() {
+                name
+            } else if name.starts_with('[') {
+                format!("{}{}", prefix, name)
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            self.transcript.push_str(&format!(
+                "{} = {}\n",
+                path,
+                value_to_string(&variable["value"])
+            ));
+
+            let children = variable["variablesReference"].as_i64().unwrap_or(0);
+            if children > 0 && depth < MAX_VARIABLE_DEPTH {
+                self.render_variables(children, &path, depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the variables of each scope of the top frame, the same as the variables pane of an IDE.
+    fn render_scopes(&mut self) -> anyhow::Result<()> {
+        let thread_id = self.current_thread()?;
+        let frame = self.top_frame(thread_id)?;
+        let body = self.request("scopes", json!({ "frameId": frame["id"] }))?;
+        let scopes = body["scopes"].as_array().cloned().unwrap_or_default();
+        for scope in scopes {
+            // Registers are not shown in the variables pane by default.
+            if scope["expensive"].as_bool() == Some(true)
+                || scope["presentationHint"].as_str() == Some("registers")
+                || scope["name"].as_str() == Some("Registers")
+            {
+                continue;
+            }
+
+            self.transcript
+                .push_str(&format!("scope = {}\n", value_to_string(&scope["name"])));
+            let variables_reference = scope["variablesReference"].as_i64().unwrap_or(0);
+            if variables_reference > 0 {
+                self.render_variables(variables_reference, "", 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the frames of the current thread as lines of `frame[i] = name`.
+    fn render_stack_trace(&mut self) -> anyhow::Result<()> {
+        let thread_id = self.current_thread()?;
+        let body = self.request("stackTrace", json!({ "threadId": thread_id }))?;
+        let frames = body["stackFrames"].as_array().cloned().unwrap_or_default();
+        for (i, frame) in frames.iter().enumerate() {
+            self.transcript.push_str(&format!(
+                "frame[{}] = {}\n",
+                i,
+                value_to_string(&frame["name"])
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Run a debugger command.
+    /// `continue`, `variables` and `stackTrace` are run with the matching requests,
+    /// all other commands are evaluated in the debug console of the top frame.
+    fn execute(&mut self, command: &str) -> anyhow::Result<()> {
+        match command {
+            "continue" => {
+                let thread_id = self.current_thread()?;
+                self.request("continue", json!({ "threadId": thread_id }))?;
+                self.run_to_break()?;
+            }
+            "variables" => self.render_scopes()?,
+            "stackTrace" => self.render_stack_trace()?,
+            expression => {
+                let thread_id = self.current_thread()?;
+                let frame = self.top_frame(thread_id)?;
+                let result = self.request(
+                    "evaluate",
+                    json!({ "expression": expression, "frameId": frame["id"], "context": "repl" }),
+                );
+                match result {
+                    Ok(body) => {
+                        self.transcript.push_str(&value_to_string(&body["result"]));
+                        self.transcript.push('\n');
+                    }
+                    Err(error) => self.transcript.push_str(&format!("error: {}\n", error)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run(
+        &mut self,
+        adapter_id: &str,
+        pid: u32,
+        program: &str,
+        breakpoints: &[BreakpointLocation],
+        debugger_script: &str,
+    ) -> anyhow::Result<()> {
+        self.request(
+            "initialize",
+            json!({
+                "clientID": "debugger_test",
+                "adapterID": adapter_id,
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "pathFormat": "path",
+            }),
+        )?;
+
+        // Some adapters only respond to the attach request once the configuration is done.
+        let attach_seq = self.send_request("attach", json!({ "pid": pid, "program": program }))?;
+        while !self.initialized {
+            // Fail early if attaching failed.
+            let attach_failed = matches!(
+                self.responses.get(&attach_seq),
+                Some(response) if response["success"].as_bool() != Some(true)
+            );
+            if attach_failed {
+                self.wait_for_response(attach_seq)?;
+            }
+            self.read_message()?;
+        }
+
+        // Add the user specified breakpoints, the source breakpoints are set for each file.
+        let mut functions = vec![json!({ "name": BREAK_FUNCTION })];
+        let mut sources: Vec<(&str, Vec<Value>)> = Vec::new();
+        for location in breakpoints {
+            match location {
+                BreakpointLocation::Function(function) => {
+                    functions.push(json!({ "name": function }))
+                }
+                BreakpointLocation::Line { file, line } => {
+                    match sources.iter_mut().find(|(source, _)| source == file) {
+                        Some((_, lines)) => lines.push(json!({ "line": line })),
+                        None => sources.push((file, vec![json!({ "line": line })])),
+                    }
+                }
+            }
+        }
+        self.has_location_breakpoints = !breakpoints.is_empty();
+
+        self.request(
+            "setFunctionBreakpoints",
+            json!({ "breakpoints": functions }),
+        )?;
+        for (file, lines) in sources {
+            self.request(
+                "setBreakpoints",
+                json!({ "source": { "path": file }, "breakpoints": lines }),
+            )?;
+        }
+        self.request("configurationDone", json!({}))?;
+        self.wait_for_response(attach_seq)?;
+
+        // Run the test executable to the first user set breakpoint.
+        self.run_to_break()?;
+
+        let commands = debugger_script
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty());
+        for (i, command) in commands.enumerate() {
+            self.transcript
+                .push_str(&format!("start_debugger_command_{}\n", i));
+            self.execute(command)?;
+            self.transcript
+                .push_str(&format!("end_debugger_command_{}\n", i));
+        }
+
+        // Detach from the test executable and let it run to completion.
+        if !self.terminated {
+            self.request("disconnect", json!({ "terminateDebuggee": false }))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait for a process to exit, killing it if it is still running after the deadline.
+fn wait_for_exit(child: &mut Child, deadline: Instant) -> anyhow::Result<bool> {
+    loop {
+        if child.try_wait()?.is_some() {
+            return Ok(true);
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(false);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Run the debugger commands of a debugger script with a debug adapter, i.e. `lldb-dap` or `gdb --interpreter=dap`.
+/// The debug adapter is attached to the test executable `program` started with `test_command`, which waits
+/// for the debugger to attach before running the test. The `breakpoints` are user specified breakpoint locations,
+/// i.e. `/path/to/src/foo.rs:42` or `my_crate::parse`, the source files should be absolute paths.
+/// Returns the output of the debugger along with the results of each request, rendered as lines of `name = value`.
+pub fn run_dap(
+    adapter_id: &str,
+    mut adapter_command: Command,
+    mut test_command: Command,
+    program: &Path,
+    breakpoints: &[String],
+    debugger_script: &str,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let breakpoints = breakpoints
+        .iter()
+        .map(|breakpoint| breakpoint.parse())
+        .collect::<anyhow::Result<Vec<BreakpointLocation>>>()?;
+
+    let mut adapter = adapter_command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdin = adapter.stdin.take().expect("stdin is piped");
+    let stdout = adapter.stdout.take().expect("stdout is piped");
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The test executable allows the debug adapter and its descendants to attach to it.
+    let mut test = match test_command
+        .env(PTRACER_ENV_VAR, adapter.id().to_string())
+        .spawn()
+    {
+        Ok(test) => test,
+        Err(error) => {
+            let _ = adapter.kill();
+            let _ = adapter.wait();
+            return Err(error.into());
+        }
+    };
+
+    let program = program.to_string_lossy().to_string();
+    let mut session = DapSession::new(stdin, receiver, timeout);
+    let result = session.run(
+        adapter_id,
+        test.id(),
+        &program,
+        &breakpoints,
+        debugger_script,
+    );
+    let deadline = session.deadline;
+    drop(session.stdin);
+
+    if let Err(error) = result {
+        let _ = adapter.kill();
+        let _ = adapter.wait();
+        let _ = test.kill();
+        let _ = test.wait();
+        anyhow::bail!("{}\n{}", error, session.transcript);
+    }
+
+    if !wait_for_exit(&mut adapter, deadline)? {
+        log::info!("The debug adapter did not exit after disconnecting, it was killed.");
+    }
+
+    if !wait_for_exit(&mut test, deadline)? {
+        anyhow::bail!(
+            "The test executable did not exit within {} seconds.\n{}",
+            timeout.as_secs(),
+            session.transcript
+        );
+    }
+
+    Ok(session.transcript)
+}
+
+#[test]
+fn test_read_and_write_message() {
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &json!({ "seq": 1, "type": "request" })).unwrap();
+    assert_eq!(
+        "Content-Length: 26\r\n\r\n{\"seq\":1,\"type\":\"request\"}",
+        String::from_utf8(buffer.clone()).unwrap()
+    );
+
+    let mut reader = std::io::Cursor::new(buffer);
+    assert_eq!(
+        Some(json!({ "seq": 1, "type": "request" })),
+        read_message(&mut reader).unwrap()
+    );
+    assert_eq!(None, read_message(&mut reader).unwrap());
+}
+
+/// Create a successful response of the debug adapter to the request with the given sequence number.
+#[cfg(test)]
+fn response(request_seq: i64, body: Value) -> Value {
+    json!({ "type": "response", "request_seq": request_seq, "success": true, "body": body })
+}
+
+/// Create an event of the debug adapter.
+#[cfg(test)]
+fn event(event: &str, body: Value) -> Value {
+    json!({ "type": "event", "event": event, "body": body })
+}
+
+#[test]
+fn test_dap_session() {
+    let (sender, receiver) = mpsc::channel();
+    let messages = vec![
+        // initialize
+        response(1, json!({})),
+        // attach, the response is sent after the configuration is done.
+        event("initialized", json!({})),
+        // setFunctionBreakpoints
+        response(3, json!({ "breakpoints": [{ "verified": true }] })),
+        // configurationDone
+        response(4, json!({})),
+        response(2, json!({})),
+        // The test executable stops when attaching and is continued.
+        event("stopped", json!({ "reason": "signal", "threadId": 7 })),
+        response(
+            5,
+            json!({ "stackFrames": [{ "id": 1, "name": "std::thread::sleep", "line": 20 }] }),
+        ),
+        response(6, json!({ "allThreadsContinued": true })),
+        // The test executable stops at the `__break` function and steps out.
+        event(
+            "stopped",
+            json!({ "reason": "function breakpoint", "threadId": 7 }),
+        ),
+        response(
+            7,
+            json!({ "stackFrames": [{ "id": 1, "name": "test::__break", "line": 4 }] }),
+        ),
+        response(8, json!({})),
+        event("stopped", json!({ "reason": "step", "threadId": 7 })),
+        response(
+            9,
+            json!({ "stackFrames": [{ "id": 2, "name": "test::test_a", "line": 12 }] }),
+        ),
+        // variables
+        response(
+            10,
+            json!({ "stackFrames": [{ "id": 2, "name": "test::test_a", "line": 12 }] }),
+        ),
+        response(
+            11,
+            json!({ "scopes": [
+            { "name": "Locals", "variablesReference": 100, "expensive": false },
+            { "name": "Registers", "variablesReference": 200, "expensive": false },
+        ] }),
+        ),
+        response(
+            12,
+            json!({ "variables": [
+            { "name": "a", "value": "10", "variablesReference": 0 },
+            { "name": "v", "value": "size=1", "variablesReference": 101 },
+        ] }),
+        ),
+        response(
+            13,
+            json!({ "variables": [{ "name": "[0]", "value": "5", "variablesReference": 0 }] }),
+        ),
+        // print a
+        response(
+            14,
+            json!({ "stackFrames": [{ "id": 2, "name": "test::test_a", "line": 12 }] }),
+        ),
+        event(
+            "output",
+            json!({ "category": "stdout", "output": "test output\n" }),
+        ),
+        response(15, json!({ "result": "(i32) 10" })),
+        // disconnect
+        response(16, json!({})),
+    ];
+    for message in messages {
+        sender.send(message).unwrap();
+    }
+
+    let mut session = DapSession::new(Vec::new(), receiver, Duration::from_secs(5));
+    session
+        .run("lldb-dap", 42, "test", &[], "variables\nprint a\n")
+        .unwrap();
+
+    assert_eq!(
+        "stopped.reason = function breakpoint\nstopped.frame.name = test::test_a\nstopped.frame.line = 12\nstart_debugger_command_0\nscope = Locals\na = 10\nv = size=1\nv[0] = 5\nend_debugger_command_0\nstart_debugger_command_1\n(i32) 10\nend_debugger_command_1\n",
+        session.transcript
+    );
+
+    let requests = String::from_utf8(session.stdin).unwrap();
+    assert!(requests.contains(r#""command":"attach""#));
+    assert!(requests.contains(r#""arguments":{"pid":42,"program":"test"}"#));
+    assert!(requests.contains(r#""arguments":{"breakpoints":[{"name":"__break"}]}"#));
+    assert!(requests.contains(r#""arguments":{"terminateDebuggee":false},"command":"disconnect""#));
+}
+
+#[test]
+fn test_dap_session_location_breakpoints() {
+    let (sender, receiver) = mpsc::channel();
+    let messages = vec![
+        // initialize
+        response(1, json!({})),
+        event("initialized", json!({})),
+        // setFunctionBreakpoints
+        response(3, json!({})),
+        // setBreakpoints
+        response(4, json!({})),
+        // configurationDone
+        response(5, json!({})),
+        response(2, json!({})),
+        // The test executable stops at the source breakpoint, which is not stepped out of.
+        event("stopped", json!({ "reason": "breakpoint", "threadId": 7 })),
+        response(
+            6,
+            json!({ "stackFrames": [{ "id": 1, "name": "test::parse", "line": 42 }] }),
+        ),
+        // disconnect
+        response(7, json!({})),
+    ];
+    for message in messages {
+        sender.send(message).unwrap();
+    }
+
+    let breakpoints = vec![
+        BreakpointLocation::Function("test::parse".to_string()),
+        BreakpointLocation::Line {
+            file: "/src/test.rs".to_string(),
+            line: 42,
+        },
+    ];
+    let mut session = DapSession::new(Vec::new(), receiver, Duration::from_secs(5));
+    session
+        .run("lldb-dap", 42, "test", &breakpoints, "")
+        .unwrap();
+
+    assert_eq!(
+        "stopped.reason = breakpoint\nstopped.frame.name = test::parse\nstopped.frame.line = 42\n",
+        session.transcript
+    );
+
+    let requests = String::from_utf8(session.stdin).unwrap();
+    assert!(requests
+        .contains(r#""arguments":{"breakpoints":[{"name":"__break"},{"name":"test::parse"}]}"#));
+    assert!(requests
+        .contains(r#""arguments":{"breakpoints":[{"line":42}],"source":{"path":"/src/test.rs"}}"#));
+}
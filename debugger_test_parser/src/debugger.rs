@@ -0,0 +1,441 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+
+#[cfg(windows)]
+pub static EXECUTABLE_EXTENSION: &str = ".exe";
+#[cfg(not(windows))]
+pub static EXECUTABLE_EXTENSION: &str = "";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebuggerType {
+    Cdb,
+    Gdb,
+    GdbMi,
+    GdbDap,
+    Lldb,
+    LldbDap,
+    RustGdb,
+    RustLldb,
+    Mock,
+}
+
+impl Display for DebuggerType {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let debugger_type = match self {
+            DebuggerType::Cdb => "cdb",
+            DebuggerType::Gdb => "gdb",
+            DebuggerType::GdbMi => "gdb-mi",
+            DebuggerType::GdbDap => "gdb-dap",
+            DebuggerType::Lldb => "lldb",
+            DebuggerType::LldbDap => "lldb-dap",
+            DebuggerType::RustGdb => "rust-gdb",
+            DebuggerType::RustLldb => "rust-lldb",
+            DebuggerType::Mock => "mock",
+        };
+        write!(fmt, "{}", debugger_type)
+    }
+}
+
+impl FromStr for DebuggerType {
+    type Err = anyhow::Error;
+
+    /// Attempts to parse a string into a DebuggerType
+    fn from_str(s: &str) -> Result<DebuggerType, Self::Err> {
+        let debugger = s.to_lowercase();
+        match debugger.as_str() {
+            "cdb" => Ok(DebuggerType::Cdb),
+            "gdb" => Ok(DebuggerType::Gdb),
+            "gdb-mi" => Ok(DebuggerType::GdbMi),
+            "gdb-dap" => Ok(DebuggerType::GdbDap),
+            "lldb" => Ok(DebuggerType::Lldb),
+            "lldb-dap" => Ok(DebuggerType::LldbDap),
+            "rust-gdb" => Ok(DebuggerType::RustGdb),
+            "rust-lldb" => Ok(DebuggerType::RustLldb),
+            "mock" => Ok(DebuggerType::Mock),
+            _ => anyhow::bail!("Invalid debugger type option: `{}`.", s),
+        }
+    }
+}
+
+/// Find the CDB debugger by searching its default installation directory.
+fn find_cdb() -> Option<OsString> {
+    // Inspired by https://github.com/rust-lang/rust/blob/1.62.0/src/tools/compiletest/src/main.rs#L821
+    let pf86 = env::var_os("ProgramFiles(x86)").or_else(|| env::var_os("ProgramFiles"))?;
+
+    let cdb_arch = if cfg!(target_arch = "x86") {
+        "x86"
+    } else if cfg!(target_arch = "x86_64") {
+        "x64"
+    } else if cfg!(target_arch = "aarch64") {
+        "arm64"
+    } else if cfg!(target_arch = "arm") {
+        "arm"
+    } else {
+        return None; // No compatible cdb.exe in the Windows 10 SDK
+    };
+
+    let mut path = PathBuf::new();
+    path.push(pf86);
+    path.push(r"Windows Kits\10\Debuggers");
+    path.push(cdb_arch);
+    path.push("cdb.exe");
+
+    if !path.exists() {
+        return None;
+    }
+
+    Some(path.into_os_string())
+}
+
+/// Find the rustup provided debugger wrapper, i.e. `rust-gdb` or `rust-lldb`,
+/// in the `bin` directory of the active toolchain's sysroot.
+fn find_rust_debugger_wrapper(wrapper: &OsString) -> Option<OsString> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| OsString::from("rustc"));
+    let output = Command::new(rustc)
+        .arg("--print")
+        .arg("sysroot")
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let sysroot = String::from_utf8(output.stdout).ok()?;
+    let path = PathBuf::from(sysroot.trim()).join("bin").join(wrapper);
+
+    if !path.exists() {
+        return None;
+    }
+
+    Some(path.into_os_string())
+}
+
+/// The name of the mock debugger executable built from the `debugger_test` package.
+static MOCK_DEBUGGER_NAME: &str = "debugger_test_mock";
+
+/// Find the mock debugger in the cargo target directory of the current test executable.
+/// Integration tests are built in `target/<profile>/deps` while binaries are built in `target/<profile>`.
+fn find_mock_debugger(mock_executable: &OsString) -> Option<OsString> {
+    let current_exe = env::current_exe().ok()?;
+    let mut dir = current_exe.parent()?;
+    if dir.file_name() == Some(std::ffi::OsStr::new("deps")) {
+        dir = dir.parent()?;
+    }
+
+    let path = dir.join(mock_executable);
+    if !path.exists() {
+        return None;
+    }
+
+    Some(path.into_os_string())
+}
+
+/// Get the debugger specified by the debugger_type parameter.
+pub fn get_debugger(debugger_type: &DebuggerType) -> PathBuf {
+    let debugger_name = match debugger_type {
+        // The GDB/MI and DAP drivers launch gdb with the `--interpreter=mi3` and `--interpreter=dap` options.
+        DebuggerType::GdbMi | DebuggerType::GdbDap => DebuggerType::Gdb.to_string(),
+        DebuggerType::Mock => MOCK_DEBUGGER_NAME.to_string(),
+        _ => debugger_type.to_string(),
+    };
+    let debugger_executable = OsString::from(format!("{}{}", debugger_name, EXECUTABLE_EXTENSION));
+
+    let debugger_env_dir = match debugger_type {
+        DebuggerType::Cdb => env::var_os("CDB_DEBUGGER_DIR"),
+        DebuggerType::Gdb | DebuggerType::GdbMi | DebuggerType::GdbDap => {
+            env::var_os("GDB_DEBUGGER_DIR")
+        }
+        DebuggerType::Lldb | DebuggerType::LldbDap => env::var_os("LLDB_DEBUGGER_DIR"),
+        DebuggerType::RustGdb => env::var_os("RUST_GDB_DEBUGGER_DIR"),
+        DebuggerType::RustLldb => env::var_os("RUST_LLDB_DEBUGGER_DIR"),
+        DebuggerType::Mock => env::var_os("MOCK_DEBUGGER_DIR"),
+    };
+
+    // First check to see if the %debugger_type%_DEBUGGER_DIR environment variable is set.
+    // If set, use this directory for all debugger invocations.
+    // If not set, fallback to the default installation directory.
+    // For the rust-gdb and rust-lldb wrappers, this is the `bin` directory of the toolchain sysroot.
+    // For the mock debugger, this is the cargo target directory of the test executable.
+    // If the debugger is not found there, fallback to the current path.
+    let debugger_executable_path = if let Some(debugger_env_path) = debugger_env_dir {
+        PathBuf::from(debugger_env_path).join(debugger_executable)
+    } else {
+        match debugger_type {
+            DebuggerType::Cdb => PathBuf::from(find_cdb().unwrap_or(debugger_executable)),
+            DebuggerType::Gdb
+            | DebuggerType::GdbMi
+            | DebuggerType::GdbDap
+            | DebuggerType::Lldb
+            | DebuggerType::LldbDap => PathBuf::from(debugger_executable),
+            DebuggerType::RustGdb | DebuggerType::RustLldb => PathBuf::from(
+                find_rust_debugger_wrapper(&debugger_executable).unwrap_or(debugger_executable),
+            ),
+            DebuggerType::Mock => PathBuf::from(
+                find_mock_debugger(&debugger_executable).unwrap_or(debugger_executable),
+            ),
+        }
+    };
+
+    debugger_executable_path
+}
+
+/// Expand the environment variables referenced in an explicit debugger path.
+/// Both the `$VAR` and `${VAR}` forms are supported.
+pub fn expand_debugger_path(debugger_path: &str) -> anyhow::Result<PathBuf> {
+    let mut expanded_path = String::new();
+    let mut chars = debugger_path.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded_path.push(c);
+            continue;
+        }
+
+        let mut var_name = String::new();
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => var_name.push(c),
+                    None => anyhow::bail!(
+                        "Missing closing `}}` for environment variable in debugger path: `{}`.",
+                        debugger_path
+                    ),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                var_name.push(c);
+                chars.next();
+            }
+        }
+
+        // A lone `$` is not a variable reference.
+        if var_name.is_empty() {
+            expanded_path.push(c);
+            continue;
+        }
+
+        match env::var(&var_name) {
+            Ok(value) => expanded_path.push_str(&value),
+            Err(_) => anyhow::bail!(
+                "Environment variable `{}` used in debugger path `{}` is not set.",
+                var_name,
+                debugger_path
+            ),
+        }
+    }
+
+    Ok(PathBuf::from(expanded_path))
+}
+
+#[test]
+#[cfg_attr(
+    not(target_os = "windows"),
+    ignore = "test only runs on windows platforms."
+)]
+fn test_find_cdb() {
+    let result = find_cdb();
+    assert!(result.is_some());
+
+    let cdb = result.unwrap();
+    let cdb_path = std::path::PathBuf::from(cdb.to_string_lossy().to_string());
+    assert!(cdb_path.file_name().is_some());
+
+    let cdb_exe = cdb_path.file_name().unwrap();
+    assert_eq!("cdb.exe", cdb_exe);
+}
+
+#[test]
+fn test_get_debugger() {
+    let debugger_type = DebuggerType::Cdb;
+    let cdb_executable = format!("cdb{}", EXECUTABLE_EXTENSION);
+
+    // Test setting the environment variable to find the debugger
+    let cdb_debugger_dir = "debugger_path/debugger";
+    env::set_var("CDB_DEBUGGER_DIR", cdb_debugger_dir);
+    assert!(env::var_os("CDB_DEBUGGER_DIR").unwrap() == OsString::from("debugger_path/debugger"));
+
+    let mut debugger_path = get_debugger(&debugger_type);
+    let expected_path = PathBuf::from(cdb_debugger_dir).join(&cdb_executable);
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("CDB_DEBUGGER_DIR");
+
+    debugger_path = get_debugger(&debugger_type);
+    assert_eq!(
+        cdb_executable,
+        debugger_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    );
+}
+
+#[test]
+fn test_get_gdb_debugger() {
+    let debugger_type = DebuggerType::Gdb;
+    let gdb_executable = format!("gdb{}", EXECUTABLE_EXTENSION);
+
+    // Test setting the environment variable to find the debugger
+    let gdb_debugger_dir = "debugger_path/gdb";
+    env::set_var("GDB_DEBUGGER_DIR", gdb_debugger_dir);
+
+    let mut debugger_path = get_debugger(&debugger_type);
+    let expected_path = PathBuf::from(gdb_debugger_dir).join(&gdb_executable);
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("GDB_DEBUGGER_DIR");
+
+    // Without the environment variable, gdb is expected to be on the PATH.
+    debugger_path = get_debugger(&debugger_type);
+    assert_eq!(PathBuf::from(&gdb_executable), debugger_path);
+
+    // The GDB/MI driver launches gdb.
+    debugger_path = get_debugger(&DebuggerType::GdbMi);
+    assert_eq!(PathBuf::from(gdb_executable), debugger_path);
+}
+
+#[test]
+fn test_get_lldb_debugger() {
+    let debugger_type = DebuggerType::Lldb;
+    let lldb_executable = format!("lldb{}", EXECUTABLE_EXTENSION);
+
+    // Test setting the environment variable to find the debugger
+    let lldb_debugger_dir = "debugger_path/lldb";
+    env::set_var("LLDB_DEBUGGER_DIR", lldb_debugger_dir);
+
+    let mut debugger_path = get_debugger(&debugger_type);
+    let expected_path = PathBuf::from(lldb_debugger_dir).join(&lldb_executable);
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("LLDB_DEBUGGER_DIR");
+
+    // Without the environment variable, lldb is expected to be on the PATH.
+    debugger_path = get_debugger(&debugger_type);
+    assert_eq!(PathBuf::from(lldb_executable), debugger_path);
+
+    // The lldb debug adapter is installed next to lldb.
+    env::set_var("LLDB_DEBUGGER_DIR", lldb_debugger_dir);
+    debugger_path = get_debugger(&DebuggerType::LldbDap);
+    let expected_path =
+        PathBuf::from(lldb_debugger_dir).join(format!("lldb-dap{}", EXECUTABLE_EXTENSION));
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("LLDB_DEBUGGER_DIR");
+}
+
+#[test]
+fn test_get_rust_gdb_debugger() {
+    let debugger_type = DebuggerType::RustGdb;
+    let rust_gdb_executable = format!("rust-gdb{}", EXECUTABLE_EXTENSION);
+
+    // Test setting the environment variable to find the debugger
+    let rust_gdb_debugger_dir = "debugger_path/rust-gdb";
+    env::set_var("RUST_GDB_DEBUGGER_DIR", rust_gdb_debugger_dir);
+
+    let mut debugger_path = get_debugger(&debugger_type);
+    let expected_path = PathBuf::from(rust_gdb_debugger_dir).join(&rust_gdb_executable);
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("RUST_GDB_DEBUGGER_DIR");
+
+    // Without the environment variable, rust-gdb is found in the sysroot or on the PATH.
+    debugger_path = get_debugger(&debugger_type);
+    assert_eq!(
+        rust_gdb_executable,
+        debugger_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    );
+}
+
+#[test]
+fn test_get_mock_debugger() {
+    let debugger_type = DebuggerType::Mock;
+    let mock_executable = format!("debugger_test_mock{}", EXECUTABLE_EXTENSION);
+
+    // Test setting the environment variable to find the debugger
+    let mock_debugger_dir = "debugger_path/mock";
+    env::set_var("MOCK_DEBUGGER_DIR", mock_debugger_dir);
+
+    let mut debugger_path = get_debugger(&debugger_type);
+    let expected_path = PathBuf::from(mock_debugger_dir).join(&mock_executable);
+    assert_eq!(expected_path, debugger_path);
+    env::remove_var("MOCK_DEBUGGER_DIR");
+
+    // Without the environment variable, the mock debugger is found in the target directory or on the PATH.
+    debugger_path = get_debugger(&debugger_type);
+    assert_eq!(
+        mock_executable,
+        debugger_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    );
+}
+
+#[test]
+fn test_debugger_type_from_str() {
+    assert!(DebuggerType::from_str("cdb").is_ok());
+    assert!(DebuggerType::from_str("gdb").is_ok());
+    assert!(DebuggerType::from_str("GDB").is_ok());
+    assert!(DebuggerType::from_str("gdb-mi").is_ok());
+    assert!(DebuggerType::from_str("gdb-dap").is_ok());
+    assert!(DebuggerType::from_str("lldb-dap").is_ok());
+    assert!(DebuggerType::from_str("lldb").is_ok());
+    assert!(DebuggerType::from_str("rust-gdb").is_ok());
+    assert!(DebuggerType::from_str("rust-lldb").is_ok());
+    assert!(DebuggerType::from_str("mock").is_ok());
+
+    let windbg_debugger_type = DebuggerType::from_str("windbg");
+    assert!(windbg_debugger_type.is_err());
+    assert_eq!(
+        "Invalid debugger type option: `windbg`.",
+        format!("{}", windbg_debugger_type.unwrap_err())
+    );
+
+    let mock_debugger_debugger_type = DebuggerType::from_str("mock debugger");
+    assert!(mock_debugger_debugger_type.is_err());
+    assert_eq!(
+        "Invalid debugger type option: `mock debugger`.",
+        format!("{}", mock_debugger_debugger_type.unwrap_err())
+    );
+}
+
+#[test]
+fn test_expand_debugger_path() {
+    env::set_var("DEBUGGER_TEST_GDB_HOME", "/opt/gdb-12");
+    assert_eq!(
+        PathBuf::from("/opt/gdb-12/bin/gdb"),
+        expand_debugger_path("$DEBUGGER_TEST_GDB_HOME/bin/gdb").unwrap()
+    );
+    assert_eq!(
+        PathBuf::from("/opt/gdb-12_bin/gdb"),
+        expand_debugger_path("${DEBUGGER_TEST_GDB_HOME}_bin/gdb").unwrap()
+    );
+    env::remove_var("DEBUGGER_TEST_GDB_HOME");
+
+    assert_eq!(
+        PathBuf::from("/usr/bin/gdb"),
+        expand_debugger_path("/usr/bin/gdb").unwrap()
+    );
+    assert_eq!(
+        PathBuf::from("a$/gdb"),
+        expand_debugger_path("a$/gdb").unwrap()
+    );
+
+    let missing_var = expand_debugger_path("$DEBUGGER_TEST_MISSING_VAR/gdb");
+    assert_eq!(
+        "Environment variable `DEBUGGER_TEST_MISSING_VAR` used in debugger path `$DEBUGGER_TEST_MISSING_VAR/gdb` is not set.",
+        format!("{}", missing_var.unwrap_err())
+    );
+}
@@ -0,0 +1,242 @@
+use serde_json::Value;
+
+/// The prefix of the lines printed by the JSON capture helper loaded into the debugger.
+pub const JSON_CAPTURE_PREFIX: &str = "debugger_test_json: ";
+
+/// A segment of a path into a JSON capture.
+#[derive(Debug, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse a path into a JSON capture, i.e. `locals.v[0]` or `expressions["a + 1"]`.
+fn parse_path(path: &str) -> anyhow::Result<Vec<PathSegment>> {
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    let mut chars = path.trim().chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if key.is_empty() && !matches!(segments.last(), Some(PathSegment::Index(_))) {
+                    anyhow::bail!("Invalid JSON path `{}`, expected a key before `.`.", path);
+                }
+                if !key.is_empty() {
+                    segments.push(PathSegment::Key(std::mem::take(&mut key)));
+                }
+            }
+            '[' => {
+                if !key.is_empty() {
+                    segments.push(PathSegment::Key(std::mem::take(&mut key)));
+                }
+
+                let mut index = String::new();
+                let mut quoted = false;
+                let mut escaped = false;
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' && !quoted {
+                        closed = true;
+                        break;
+                    }
+                    if c == '"' && !escaped {
+                        quoted = !quoted;
+                    }
+                    escaped = c == '\\' && !escaped;
+                    index.push(c);
+                }
+
+                if !closed {
+                    anyhow::bail!("Invalid JSON path `{}`, expected `]`.", path);
+                }
+
+                let index = index.trim();
+                if index.starts_with('"') {
+                    let key: String = serde_json::from_str(index).map_err(|error| {
+                        anyhow::anyhow!("Invalid JSON path `{}`: {}", path, error)
+                    })?;
+                    segments.push(PathSegment::Key(key));
+                } else {
+                    let index = index.parse::<usize>().map_err(|_| {
+                        anyhow::anyhow!(
+                            "Invalid JSON path `{}`, expected an index or a quoted key in `[]`.",
+                            path
+                        )
+                    })?;
+                    segments.push(PathSegment::Index(index));
+                }
+            }
+            c => key.push(c),
+        }
+    }
+
+    if !key.is_empty() {
+        segments.push(PathSegment::Key(key));
+    }
+
+    if segments.is_empty() {
+        anyhow::bail!("Invalid JSON path `{}`, expected a key.", path);
+    }
+
+    Ok(segments)
+}
+
+fn query_segments<'a>(value: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    let mut value = value;
+    for segment in segments {
+        value = match segment {
+            PathSegment::Key(key) => value.get(key.as_str())?,
+            PathSegment::Index(index) => value.get(*index)?,
+        };
+    }
+
+    Some(value)
+}
+
+/// Get the value at a path into a JSON capture, i.e. `locals.v[0]` or `expressions["a + 1"]`.
+/// Returns `None` if the capture has no value at the path.
+pub fn query_json<'a>(capture: &'a Value, path: &str) -> anyhow::Result<Option<&'a Value>> {
+    Ok(query_segments(capture, &parse_path(path)?))
+}
+
+/// Parse the JSON capture printed on a line of debugger output, the debugger prompt can precede it.
+fn parse_capture(line: &str) -> Option<Value> {
+    let index = line.find(JSON_CAPTURE_PREFIX)?;
+    serde_json::from_str(&line[index + JSON_CAPTURE_PREFIX.len()..]).ok()
+}
+
+/// Get the JSON captures printed by the JSON capture helper, one for each time the test stopped at a breakpoint.
+pub fn json_captures(debugger_output: &str) -> Vec<Value> {
+    debugger_output.lines().filter_map(parse_capture).collect()
+}
+
+/// Parse an expected value as JSON, a value which is not valid JSON is expected to be a string.
+pub(crate) fn parse_expected_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// An expected value in a JSON capture, i.e. `json:locals.a = 10`.
+pub(crate) struct JsonExpectation {
+    path: String,
+    segments: Vec<PathSegment>,
+    value: Value,
+}
+
+impl JsonExpectation {
+    /// Parse an expected value of the form `<path> = <value>`.
+    /// The value is parsed as JSON, a value which is not valid JSON is expected to be a string.
+    pub(crate) fn parse(expected: &str) -> anyhow::Result<Self> {
+        // Find the `=` which is not within the brackets of the path.
+        let mut depth = 0;
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut separator = None;
+        for (index, c) in expected.char_indices() {
+            match c {
+                '"' if !escaped => quoted = !quoted,
+                '[' if !quoted => depth += 1,
+                ']' if !quoted => depth -= 1,
+                '=' if !quoted && depth == 0 => {
+                    separator = Some(index);
+                    break;
+                }
+                _ => {}
+            }
+            escaped = c == '\\' && !escaped;
+        }
+
+        let separator = separator.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid JSON expectation `{}`, expected `<path> = <value>`.",
+                expected
+            )
+        })?;
+        let path = expected[..separator].trim();
+        let value = expected[separator + 1..].trim();
+
+        Ok(JsonExpectation {
+            path: path.to_string(),
+            segments: parse_path(path)?,
+            value: parse_expected_value(value),
+        })
+    }
+
+    /// Check whether the line of debugger output is a JSON capture with the expected value.
+    pub(crate) fn is_match(&self, line: &str) -> bool {
+        match parse_capture(line) {
+            Some(capture) => query_segments(&capture, &self.segments) == Some(&self.value),
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Display for JsonExpectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.path, self.value)
+    }
+}
+
+#[test]
+fn test_parse_path() {
+    assert_eq!(
+        vec![
+            PathSegment::Key("locals".to_string()),
+            PathSegment::Key("v".to_string()),
+            PathSegment::Index(1),
+            PathSegment::Key("x".to_string()),
+        ],
+        parse_path("locals.v[1].x").unwrap()
+    );
+    assert_eq!(
+        vec![
+            PathSegment::Key("expressions".to_string()),
+            PathSegment::Key("v[0] + \"]\"".to_string()),
+        ],
+        parse_path(r#"expressions["v[0] + \"]\""]"#).unwrap()
+    );
+    assert_eq!(
+        "Invalid JSON path `locals[a]`, expected an index or a quoted key in `[]`.",
+        parse_path("locals[a]").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_json_captures() {
+    let output = "start_debugger_command_0\n(gdb) debugger_test_json: {\"function\": \"test::f\", \"locals\": {\"a\": 10, \"v\": [1, 2]}, \"expressions\": {\"a + 1\": 11}}\nend_debugger_command_0\n";
+    let captures = json_captures(output);
+    assert_eq!(1, captures.len());
+    assert_eq!(
+        Some(&Value::from(2)),
+        query_json(&captures[0], "locals.v[1]").unwrap()
+    );
+    assert_eq!(
+        Some(&Value::from(11)),
+        query_json(&captures[0], r#"expressions["a + 1"]"#).unwrap()
+    );
+    assert_eq!(None, query_json(&captures[0], "locals.b").unwrap());
+}
+
+#[test]
+fn test_json_expectation() {
+    let line = r#"debugger_test_json: {"function": "test::f", "locals": {"a": 10, "s": "hi", "v": [1, 2]}}"#;
+    assert!(JsonExpectation::parse("locals.a = 10")
+        .unwrap()
+        .is_match(line));
+    assert!(JsonExpectation::parse("locals.s = hi")
+        .unwrap()
+        .is_match(line));
+    assert!(JsonExpectation::parse("locals.v = [1, 2]")
+        .unwrap()
+        .is_match(line));
+    assert!(!JsonExpectation::parse("locals.a = \"10\"")
+        .unwrap()
+        .is_match(line));
+    assert_eq!(
+        "Invalid JSON expectation `locals.a`, expected `<path> = <value>`.",
+        JsonExpectation::parse("locals.a")
+            .err()
+            .unwrap()
+            .to_string()
+    );
+}
@@ -1,11 +1,48 @@
+mod attach;
+mod breakpoint;
+mod capture;
+mod dap;
+mod debugger;
+mod json;
+mod mi;
+mod session;
+mod transcript;
+mod watch;
+
 use regex::Regex;
 
+use crate::json::JsonExpectation;
+use crate::watch::WatchExpectation;
+
+pub use attach::{
+    allow_debugger_attach, wait_for_debugger, wait_for_debugger_attach, wait_for_debugger_exit,
+    DebuggerAttachGuard,
+};
+pub use breakpoint::{break_label_function, BreakpointLocation, BREAK_LABEL_FUNCTION_PREFIX};
+pub use capture::{capture_names, capture_references};
+pub use dap::run_dap;
+pub use debugger::{expand_debugger_path, get_debugger, DebuggerType};
+pub use json::{json_captures, query_json, JSON_CAPTURE_PREFIX};
+pub use mi::run_gdb_mi;
+pub use serde_json::Value as JsonValue;
+pub use session::run_session;
+pub use transcript::{record_transcript, replay_transcript};
+pub use watch::{watch_triggers, WatchTrigger, WATCH_PREFIX};
+
 enum OutputParsingStyle {
     LiteralMatch(String),
     PatternMatch(Regex),
+    Json(JsonExpectation),
+    Watch(WatchExpectation),
 }
 
 const PATTERN_PREFIX: &str = "pattern:";
+const JSON_PREFIX: &str = "json:";
+const WATCH_EXPECTATION_PREFIX: &str = "watch:";
+const START_BREAK_LABEL_PREFIX: &str = "start_break_label_";
+const END_BREAK_LABEL_PREFIX: &str = "end_break_label_";
+const START_SETUP_PREFIX: &str = "start_setup_command_";
+const END_SETUP_PREFIX: &str = "end_setup_command_";
 
 /// Parse the output of a debugger and verify that the expected contents
 /// are found. If content was expected in the debugger output that is not
@@ -39,7 +76,7 @@ pub fn parse(debugger_output: String, expected_contents: Vec<&str>) -> anyhow::R
 
     let mut index = 0;
 
-    for expected in expected_contents {
+    for (expected_index, expected) in expected_contents.iter().enumerate() {
         let parsing_style = get_output_parsing_style(expected)?;
         loop {
             if index >= debugger_output_lines.len() {
@@ -72,6 +109,24 @@ pub fn parse(debugger_output: String, expected_contents: Vec<&str>) -> anyhow::R
                         break;
                     }
                 }
+                OutputParsingStyle::Json(expectation) => {
+                    if expectation.is_match(debugger_output_line) {
+                        log::info!("Expected JSON value found: `{}`", expectation);
+                        // A following `json:` expectation can be in the same JSON capture, any other expectation
+                        // is searched for after it.
+                        let next_expected = expected_contents.get(expected_index + 1);
+                        if matches!(next_expected, Some(next) if next.starts_with(JSON_PREFIX)) {
+                            index -= 1;
+                        }
+                        break;
+                    }
+                }
+                OutputParsingStyle::Watch(expectation) => {
+                    if expectation.is_match(debugger_output_line) {
+                        log::info!("Expected watchpoint write found: `{}`", expectation);
+                        break;
+                    }
+                }
             }
         }
     }
@@ -79,6 +134,73 @@ pub fn parse(debugger_output: String, expected_contents: Vec<&str>) -> anyhow::R
     anyhow::Ok(())
 }
 
+/// Parse the output of the debugger commands run at the breakpoints of a `break_here!` label and verify
+/// that the expected contents are found. The output of each time the label was hit is verified in order.
+pub fn parse_break_label(
+    debugger_output: &str,
+    label: &str,
+    expected_contents: Vec<&str>,
+) -> anyhow::Result<()> {
+    let start_marker = format!("{}{}", START_BREAK_LABEL_PREFIX, label);
+    let end_marker = format!("{}{}", END_BREAK_LABEL_PREFIX, label);
+
+    // Only keep the output between the markers of the label, the debugger prompt can precede a marker.
+    let mut label_output = String::new();
+    let mut hits = 0;
+    let mut in_label = false;
+    for line in debugger_output.lines() {
+        let trimmed = line.trim();
+        if trimmed.ends_with(&start_marker) {
+            in_label = true;
+            hits += 1;
+        } else if trimmed.ends_with(&end_marker) {
+            in_label = false;
+        } else if in_label {
+            label_output.push_str(line);
+            label_output.push('\n');
+        }
+    }
+
+    if hits == 0 && expected_contents.iter().any(|line| !line.trim().is_empty()) {
+        anyhow::bail!("The breakpoint label `{}` was never hit.", label);
+    }
+
+    parse(label_output, expected_contents)
+        .map_err(|error| anyhow::anyhow!("At breakpoint label `{}`: {}", label, error))
+}
+
+/// Check whether a line of debugger output is a marker with the given prefix, i.e. `start_setup_command_0`.
+/// The debugger prompt and the command printing the marker can precede it.
+fn is_marker(line: &str, prefix: &str) -> bool {
+    match line.trim().rfind(prefix) {
+        Some(index) => {
+            let number = &line.trim()[index + prefix.len()..];
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Parse the output of the setup commands, which are run before the test starts, and verify that the expected
+/// contents are found. Only the output of the setup commands is verified.
+pub fn parse_setup(debugger_output: &str, expected_contents: Vec<&str>) -> anyhow::Result<()> {
+    let mut setup_output = String::new();
+    let mut in_setup = false;
+    for line in debugger_output.lines() {
+        if is_marker(line, START_SETUP_PREFIX) {
+            in_setup = true;
+        } else if is_marker(line, END_SETUP_PREFIX) {
+            in_setup = false;
+        } else if in_setup {
+            setup_output.push_str(line);
+            setup_output.push('\n');
+        }
+    }
+
+    parse(setup_output, expected_contents)
+        .map_err(|error| anyhow::anyhow!("In the setup commands: {}", error))
+}
+
 fn format_error_message(parsing_style: &OutputParsingStyle) -> String {
     match parsing_style {
         OutputParsingStyle::LiteralMatch(literal_string) => {
@@ -87,6 +209,12 @@ fn format_error_message(parsing_style: &OutputParsingStyle) -> String {
         OutputParsingStyle::PatternMatch(pattern) => {
             format!("Found 0 matches for pattern: `{}`", pattern.to_string())
         }
+        OutputParsingStyle::Json(expectation) => {
+            format!("Found 0 JSON captures with: `{}`", expectation)
+        }
+        OutputParsingStyle::Watch(expectation) => {
+            format!("Found 0 watchpoint writes with: `{}`", expectation)
+        }
     }
 }
 
@@ -102,6 +230,10 @@ fn get_output_parsing_style(expected_output: &str) -> anyhow::Result<OutputParsi
         };
 
         OutputParsingStyle::PatternMatch(re)
+    } else if let Some(expected_value) = expected_output.strip_prefix(JSON_PREFIX) {
+        OutputParsingStyle::Json(JsonExpectation::parse(expected_value)?)
+    } else if let Some(expected_write) = expected_output.strip_prefix(WATCH_EXPECTATION_PREFIX) {
+        OutputParsingStyle::Watch(WatchExpectation::parse(expected_write)?)
     } else {
         OutputParsingStyle::LiteralMatch(String::from(expected_output))
     };
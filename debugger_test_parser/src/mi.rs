@@ -0,0 +1,595 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The function called by tests at each place the debugger should stop.
+/// The driver steps out of it automatically, the same as the `finish` breakpoint command of the gdb script.
+const BREAK_FUNCTION: &str = "__break";
+
+/// The interval used when polling for gdb to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A value in a GDB/MI output record.
+/// See https://sourceware.org/gdb/onlinedocs/gdb/GDB_002fMI-Output-Syntax.html
+#[derive(Clone, Debug, PartialEq)]
+enum MiValue {
+    Const(String),
+    Tuple(Vec<(String, MiValue)>),
+    List(Vec<MiValue>),
+}
+
+/// A line of GDB/MI output.
+#[derive(Clone, Debug, PartialEq)]
+enum MiRecord {
+    /// A result record, i.e. `^done,value="10"`.
+    Result {
+        class: String,
+        results: Vec<(String, MiValue)>,
+    },
+    /// An async record, i.e. `*stopped,reason="breakpoint-hit"` or `=thread-created,id="1"`.
+    Async {
+        kind: char,
+        class: String,
+        results: Vec<(String, MiValue)>,
+    },
+    /// A stream record, i.e. `~"$1 = 10\n"`.
+    Stream { kind: char, text: String },
+    /// The `(gdb)` prompt.
+    Prompt,
+    /// Output which is not a GDB/MI record, i.e. output of the inferior.
+    Other(String),
+}
+
+struct MiParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MiParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    /// Parse a C string, decoding its escape sequences.
+    fn parse_cstring(&mut self) -> Option<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'0'..=b'7' => {
+                            // Octal escapes are used for non-printable and non-ASCII bytes.
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            bytes.push(value as u8);
+                        }
+                        c => bytes.push(c),
+                    }
+                }
+                c => bytes.push(c),
+            }
+        }
+
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Parse the name of a result or the class of a record.
+    fn parse_identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == b'_' || c == b'-') {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&self.input[start..self.pos]).into_owned())
+    }
+
+    fn parse_result(&mut self) -> Option<(String, MiValue)> {
+        let name = self.parse_identifier()?;
+        self.expect(b'=')?;
+        Some((name, self.parse_value()?))
+    }
+
+    fn parse_value(&mut self) -> Option<MiValue> {
+        match self.peek()? {
+            b'"' => Some(MiValue::Const(self.parse_cstring()?)),
+            b'{' => {
+                self.pos += 1;
+                let mut results = Vec::new();
+                if self.expect(b'}').is_none() {
+                    loop {
+                        results.push(self.parse_result()?);
+                        if self.expect(b',').is_none() {
+                            break;
+                        }
+                    }
+                    self.expect(b'}')?;
+                }
+                Some(MiValue::Tuple(results))
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if self.expect(b']').is_none() {
+                    loop {
+                        // Lists contain either values or results, results are kept as a tuple of a single result.
+                        match self.peek()? {
+                            b'"' | b'{' | b'[' => values.push(self.parse_value()?),
+                            _ => values.push(MiValue::Tuple(vec![self.parse_result()?])),
+                        }
+                        if self.expect(b',').is_none() {
+                            break;
+                        }
+                    }
+                    self.expect(b']')?;
+                }
+                Some(MiValue::List(values))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_results(&mut self) -> Option<Vec<(String, MiValue)>> {
+        let mut results = Vec::new();
+        while self.expect(b',').is_some() {
+            results.push(self.parse_result()?);
+        }
+        Some(results)
+    }
+
+    fn parse_record(&mut self) -> Option<MiRecord> {
+        // Skip the optional token of the command the record is for.
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+
+        let kind = self.peek()? as char;
+        self.pos += 1;
+        let record = match kind {
+            '^' => {
+                let class = self.parse_identifier()?;
+                let results = self.parse_results()?;
+                MiRecord::Result { class, results }
+            }
+            '*' | '+' | '=' => {
+                let class = self.parse_identifier()?;
+                let results = self.parse_results()?;
+                MiRecord::Async {
+                    kind,
+                    class,
+                    results,
+                }
+            }
+            '~' | '@' | '&' => MiRecord::Stream {
+                kind,
+                text: self.parse_cstring()?,
+            },
+            _ => return None,
+        };
+
+        if !self.is_done() {
+            return None;
+        }
+
+        Some(record)
+    }
+}
+
+/// Parse a line of GDB/MI output.
+fn parse_record(line: &str) -> MiRecord {
+    let line = line.trim_end();
+    if line == "(gdb)" {
+        return MiRecord::Prompt;
+    }
+
+    let mut parser = MiParser {
+        input: line.as_bytes(),
+        pos: 0,
+    };
+    parser
+        .parse_record()
+        .unwrap_or_else(|| MiRecord::Other(line.to_string()))
+}
+
+/// Get the value of a result with the given name.
+fn find_result<'a>(results: &'a [(String, MiValue)], name: &str) -> Option<&'a MiValue> {
+    results
+        .iter()
+        .find(|(result_name, _)| result_name == name)
+        .map(|(_, value)| value)
+}
+
+/// Render a value as lines of `path = value`.
+/// Tuples with a `name` and a `value`, i.e. the variables of a frame, are rendered as `name = value`.
+fn render_value(path: &str, value: &MiValue, output: &mut String) {
+    match value {
+        MiValue::Const(value) => output.push_str(&format!("{} = {}\n", path, value)),
+        MiValue::Tuple(results) => {
+            if let (Some(MiValue::Const(name)), Some(value)) =
+                (find_result(results, "name"), find_result(results, "value"))
+            {
+                return render_value(name, value, output);
+            }
+            render_results(path, results, output);
+        }
+        MiValue::List(values) => {
+            for (i, value) in values.iter().enumerate() {
+                render_value(&format!("{}[{}]", path, i), value, output);
+            }
+        }
+    }
+}
+
+/// Render the results of a record as lines of `path = value`.
+fn render_results(prefix: &str, results: &[(String, MiValue)], output: &mut String) {
+    for (name, value) in results {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        render_value(&path, value, output);
+    }
+}
+
+/// Check if the debugger stopped at a breakpoint in the `__break` function.
+fn is_break_function_hit(results: &[(String, MiValue)]) -> bool {
+    let is_breakpoint_hit =
+        find_result(results, "reason") == Some(&MiValue::Const(String::from("breakpoint-hit")));
+    let func = match find_result(results, "frame") {
+        Some(MiValue::Tuple(frame)) => find_result(frame, "func"),
+        _ => None,
+    };
+
+    match func {
+        Some(MiValue::Const(func)) => is_breakpoint_hit && func.ends_with(BREAK_FUNCTION),
+        _ => false,
+    }
+}
+
+/// A GDB/MI session which sends the commands of a debugger script to gdb and
+/// collects the stream output and the structured results of each command.
+struct GdbMiSession<W: Write> {
+    stdin: W,
+    receiver: Receiver<String>,
+    deadline: Instant,
+    timeout: Duration,
+    transcript: String,
+}
+
+impl<W: Write> GdbMiSession<W> {
+    fn send(&mut self, command: &str) -> anyhow::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn read_record(&mut self) -> anyhow::Result<MiRecord> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        match self.receiver.recv_timeout(remaining) {
+            Ok(line) => Ok(parse_record(&line)),
+            Err(RecvTimeoutError::Timeout) => anyhow::bail!(
+                "gdb did not respond within {} seconds.",
+                self.timeout.as_secs()
+            ),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("gdb exited unexpectedly."),
+        }
+    }
+
+    /// Run a GDB/MI command, waiting for its result and for the inferior to stop if the command resumed it.
+    /// Returns `false` if gdb exited.
+    fn execute(&mut self, command: &str) -> anyhow::Result<bool> {
+        self.send(command)?;
+
+        let mut has_result = false;
+        let mut running = false;
+        let mut step_out = false;
+        loop {
+            match self.read_record()? {
+                MiRecord::Result { class, results } => {
+                    match class.as_str() {
+                        "running" => running = true,
+                        "exit" => return Ok(false),
+                        "error" => match find_result(&results, "msg") {
+                            Some(MiValue::Const(msg)) => {
+                                self.transcript.push_str(&format!("error: {}\n", msg))
+                            }
+                            _ => self.transcript.push_str("error\n"),
+                        },
+                        _ => render_results("", &results, &mut self.transcript),
+                    }
+                    has_result = true;
+                }
+                MiRecord::Async {
+                    kind: '*',
+                    class,
+                    results,
+                } if class == "stopped" => {
+                    render_results("stopped", &results, &mut self.transcript);
+                    running = false;
+                    step_out = is_break_function_hit(&results);
+                }
+                MiRecord::Stream { kind: '~', text } | MiRecord::Stream { kind: '@', text } => {
+                    self.transcript.push_str(&text)
+                }
+                MiRecord::Other(line) => {
+                    self.transcript.push_str(&line);
+                    self.transcript.push('\n');
+                }
+                _ => {}
+            }
+
+            if has_result && !running {
+                if !step_out {
+                    return Ok(true);
+                }
+
+                // Step out of the `__break` function to the frame of the test.
+                self.send("-exec-finish")?;
+                has_result = false;
+                step_out = false;
+            }
+        }
+    }
+
+    fn run(&mut self, debugger_script: &str) -> anyhow::Result<()> {
+        for command in debugger_script.lines().map(|line| line.trim()) {
+            if command.is_empty() {
+                continue;
+            }
+
+            if !self.execute(command)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a debugger script of GDB/MI commands with gdb, i.e. `gdb --interpreter=mi3`.
+/// Returns the stream output of gdb along with the structured results of each command,
+/// rendered as lines of `name = value`.
+pub fn run_gdb_mi(
+    mut command: Command,
+    debugger_script: &str,
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let sent = match line {
+                Ok(line) => sender.send(line).is_ok(),
+                Err(_) => false,
+            };
+            if !sent {
+                break;
+            }
+        }
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut session = GdbMiSession {
+        stdin,
+        receiver,
+        deadline,
+        timeout,
+        transcript: String::new(),
+    };
+
+    if let Err(error) = session.run(debugger_script) {
+        let _ = child.kill();
+        let _ = child.wait();
+        anyhow::bail!("{}\n{}", error, session.transcript);
+    }
+
+    // Wait for gdb to exit.
+    drop(session.stdin);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                anyhow::bail!("gdb failed with {}.\n{}", status, session.transcript);
+            }
+            break;
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!(
+                "gdb did not exit within {} seconds.\n{}",
+                timeout.as_secs(),
+                session.transcript
+            );
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(session.transcript)
+}
+
+#[test]
+fn test_parse_record() {
+    assert_eq!(MiRecord::Prompt, parse_record("(gdb) "));
+    assert_eq!(
+        MiRecord::Stream {
+            kind: '~',
+            text: String::from("$1 = \"a\\b\"\n")
+        },
+        parse_record(r#"~"$1 = \"a\\b\"\n""#)
+    );
+    assert_eq!(
+        MiRecord::Result {
+            class: String::from("done"),
+            results: vec![(
+                String::from("variables"),
+                MiValue::List(vec![MiValue::Tuple(vec![
+                    (String::from("name"), MiValue::Const(String::from("a"))),
+                    (String::from("value"), MiValue::Const(String::from("10"))),
+                ])])
+            )]
+        },
+        parse_record(r#"12^done,variables=[{name="a",value="10"}]"#)
+    );
+    assert_eq!(
+        MiRecord::Async {
+            kind: '*',
+            class: String::from("running"),
+            results: vec![(
+                String::from("thread-id"),
+                MiValue::Const(String::from("all"))
+            )]
+        },
+        parse_record(r#"*running,thread-id="all""#)
+    );
+    assert_eq!(
+        MiRecord::Other(String::from("running 1 test")),
+        parse_record("running 1 test")
+    );
+    assert_eq!(
+        MiRecord::Other(String::from("^done,value=\"10")),
+        parse_record("^done,value=\"10")
+    );
+}
+
+#[test]
+fn test_render_results() {
+    let mut output = String::new();
+    match parse_record(
+        r#"*stopped,reason="function-finished",frame={func="test::test_a",args=[],line="12"},stack=[frame={level="0"}]"#,
+    ) {
+        MiRecord::Async { results, .. } => render_results("stopped", &results, &mut output),
+        record => panic!("unexpected record {:?}", record),
+    }
+    assert_eq!(
+        "stopped.reason = function-finished\nstopped.frame.func = test::test_a\nstopped.frame.line = 12\nstopped.stack[0].frame.level = 0\n",
+        output
+    );
+
+    let mut output = String::new();
+    match parse_record(r#"^done,variables=[{name="a",value="10"},{name="b",type="u8",value="5"}]"#)
+    {
+        MiRecord::Result { results, .. } => render_results("", &results, &mut output),
+        record => panic!("unexpected record {:?}", record),
+    }
+    assert_eq!("a = 10\nb = 5\n", output);
+}
+
+#[test]
+fn test_gdb_mi_session() {
+    let (sender, receiver) = mpsc::channel();
+    let output = [
+        r#"=thread-group-added,id="i1""#,
+        "(gdb) ",
+        r#"^done,bkpt={number="1",func="test::__break"}"#,
+        "(gdb) ",
+        r#"=thread-group-started,id="i1",pid="42""#,
+        "^running",
+        r#"*running,thread-id="all""#,
+        "(gdb) ",
+        r#"*stopped,reason="breakpoint-hit",bkptno="1",frame={func="test::__break",line="4"}"#,
+        "(gdb) ",
+        "^running",
+        "(gdb) ",
+        r#"*stopped,reason="function-finished",frame={func="test::test_a",line="12"}"#,
+        "(gdb) ",
+        r#"~"start_debugger_command_0\n""#,
+        "^done",
+        "(gdb) ",
+        r#"&"print a\n""#,
+        r#"~"$1 = 10\n""#,
+        "^done",
+        "(gdb) ",
+        r#"~"end_debugger_command_0\n""#,
+        "^done",
+        "(gdb) ",
+        "^exit",
+    ];
+    for line in output.iter() {
+        sender.send(line.to_string()).unwrap();
+    }
+
+    let mut session = GdbMiSession {
+        stdin: Vec::new(),
+        receiver,
+        deadline: Instant::now() + Duration::from_secs(5),
+        timeout: Duration::from_secs(5),
+        transcript: String::new(),
+    };
+    session
+        .run(
+            "-break-insert -f __break\n-exec-run\n-interpreter-exec console \"echo start_debugger_command_0\\\\n\"\n-interpreter-exec console \"print a\"\n-interpreter-exec console \"echo end_debugger_command_0\\\\n\"\n-gdb-exit\n",
+        )
+        .unwrap();
+
+    assert_eq!(
+        "-break-insert -f __break\n-exec-run\n-exec-finish\n-interpreter-exec console \"echo start_debugger_command_0\\\\n\"\n-interpreter-exec console \"print a\"\n-interpreter-exec console \"echo end_debugger_command_0\\\\n\"\n-gdb-exit\n",
+        String::from_utf8(session.stdin).unwrap()
+    );
+    assert_eq!(
+        "bkpt.number = 1\nbkpt.func = test::__break\nstopped.reason = breakpoint-hit\nstopped.bkptno = 1\nstopped.frame.func = test::__break\nstopped.frame.line = 4\nstopped.reason = function-finished\nstopped.frame.func = test::test_a\nstopped.frame.line = 12\nstart_debugger_command_0\n$1 = 10\nend_debugger_command_0\n",
+        session.transcript
+    );
+}
+
+#[test]
+fn test_gdb_mi_session_timeout() {
+    let (_sender, receiver) = mpsc::channel();
+    let mut session = GdbMiSession {
+        stdin: Vec::new(),
+        receiver,
+        deadline: Instant::now(),
+        timeout: Duration::from_secs(0),
+        transcript: String::new(),
+    };
+    assert_eq!(
+        "gdb did not respond within 0 seconds.",
+        session.run("-exec-run").unwrap_err().to_string()
+    );
+}
@@ -0,0 +1,405 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::capture::Captures;
+use crate::DebuggerType;
+
+/// The text printed by the debugger once it has run all of the commands sent to it.
+const SYNC_MARKER: &str = "__debugger_test_sync_";
+
+/// The text of the markers printed around each user specified debugger command.
+const START_MARKER: &str = "start_debugger_command_";
+const END_MARKER: &str = "end_debugger_command_";
+
+/// The interval used when polling for the debugger to exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A part of the debugger script which is sent to the debugger at once.
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// The commands which run the test to the first breakpoint.
+    Setup(String),
+    /// A user specified debugger command along with the commands printing the markers around it.
+    Command(String),
+    /// The commands which run the test to completion and quit the debugger.
+    Teardown(String),
+}
+
+/// Split a debugger script into the steps which are sent to the debugger one at a time.
+fn split_script(debugger_script: &str) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut commands = String::new();
+    let mut has_command = false;
+
+    for line in debugger_script.lines() {
+        if line.contains(START_MARKER) && !commands.trim().is_empty() {
+            let commands = std::mem::take(&mut commands);
+            if has_command {
+                steps.push(Step::Command(commands));
+            } else {
+                steps.push(Step::Setup(commands));
+            }
+        }
+
+        commands.push_str(line);
+        commands.push('\n');
+
+        if line.contains(END_MARKER) {
+            steps.push(Step::Command(std::mem::take(&mut commands)));
+            has_command = true;
+        }
+    }
+
+    if !commands.trim().is_empty() {
+        steps.push(Step::Teardown(commands));
+    }
+
+    steps
+}
+
+/// Get the debugger command which prints the sync marker with the given id.
+/// The marker is formatted by the debugger so that an echo of the command itself is never taken for the marker.
+fn sync_command(debugger_type: DebuggerType, id: usize) -> String {
+    match debugger_type {
+        DebuggerType::Cdb => format!(".printf \"{}%d\\n\", 0n{}", SYNC_MARKER, id),
+        DebuggerType::Gdb | DebuggerType::GdbMi | DebuggerType::GdbDap | DebuggerType::RustGdb => {
+            format!("printf \"{}%d\\n\", {}", SYNC_MARKER, id)
+        }
+        DebuggerType::Lldb | DebuggerType::LldbDap | DebuggerType::RustLldb => {
+            format!("script print(\"{}%d\" % {})", SYNC_MARKER, id)
+        }
+        // The mock debugger never echoes the commands it runs.
+        DebuggerType::Mock => format!(".echo {}{}", SYNC_MARKER, id),
+    }
+}
+
+/// Get the pattern of the output printed by the debugger when the test executable exits.
+fn exit_pattern(debugger_type: DebuggerType) -> Option<Regex> {
+    let pattern = match debugger_type {
+        DebuggerType::Cdb => r"\bNtTerminateProcess\b",
+        DebuggerType::Gdb | DebuggerType::GdbMi | DebuggerType::GdbDap | DebuggerType::RustGdb => {
+            r"\[Inferior \d+ \(process \d+\) exited"
+        }
+        DebuggerType::Lldb | DebuggerType::LldbDap | DebuggerType::RustLldb => {
+            r"Process \d+ exited with status"
+        }
+        DebuggerType::Mock => return None,
+    };
+
+    Some(Regex::new(pattern).expect("exit pattern is valid"))
+}
+
+struct DebuggerSession<W: Write> {
+    debugger_type: DebuggerType,
+    stdin: W,
+    receiver: Receiver<String>,
+    exit_pattern: Option<Regex>,
+    captures: Captures,
+    next_sync: usize,
+    /// Set once the debugger has closed its output.
+    closed: bool,
+    /// Set once the test executable has exited.
+    exited: bool,
+    transcript: String,
+}
+
+impl<W: Write> DebuggerSession<W> {
+    fn new(
+        debugger_type: DebuggerType,
+        stdin: W,
+        receiver: Receiver<String>,
+        captures: Captures,
+    ) -> Self {
+        DebuggerSession {
+            debugger_type,
+            stdin,
+            receiver,
+            exit_pattern: exit_pattern(debugger_type),
+            captures,
+            next_sync: 0,
+            closed: false,
+            exited: false,
+            transcript: String::new(),
+        }
+    }
+
+    /// Send commands to the debugger and collect their output until the debugger prints the sync marker.
+    /// The output is collected until the debugger exits when `sync` is false.
+    fn execute(&mut self, commands: &str, timeout: Duration, sync: bool) -> anyhow::Result<String> {
+        write!(self.stdin, "{}", commands)?;
+        let marker = format!("{}{}", SYNC_MARKER, self.next_sync);
+        if sync {
+            writeln!(
+                self.stdin,
+                "{}",
+                sync_command(self.debugger_type, self.next_sync)
+            )?;
+            self.next_sync += 1;
+        }
+        self.stdin.flush()?;
+
+        let deadline = Instant::now() + timeout;
+        let mut output = String::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(remaining) {
+                Ok(line) => {
+                    // The debugger prompt can precede the sync marker.
+                    if sync && line.trim_end().ends_with(&marker) {
+                        break;
+                    }
+
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.transcript.push_str(&output);
+                    anyhow::bail!(
+                        "The debugger did not finish running `{}` within {} seconds.",
+                        commands.trim().replace('\n', "; "),
+                        timeout.as_secs()
+                    );
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        if let Some(exit_pattern) = &self.exit_pattern {
+            if exit_pattern.is_match(&output) {
+                self.exited = true;
+            }
+        }
+
+        self.captures.capture(&output);
+
+        self.transcript.push_str(&output);
+        Ok(output)
+    }
+
+    fn run(&mut self, debugger_script: &str, timeout: Duration) -> anyhow::Result<()> {
+        for step in split_script(debugger_script) {
+            if self.closed {
+                break;
+            }
+
+            match step {
+                Step::Setup(commands) => {
+                    self.execute(&commands, timeout, true)?;
+                }
+                // Stop running debugger commands once the test executable has exited.
+                Step::Command(commands) if self.exited => {
+                    log::info!(
+                        "Skipping `{}`, the test executable has exited.",
+                        commands.trim().replace('\n', "; ")
+                    );
+                }
+                // Substitute the values captured from the output of the earlier debugger commands.
+                Step::Command(commands) => {
+                    let mut substituted = String::new();
+                    for line in commands.lines() {
+                        substituted.push_str(&self.captures.substitute(line)?);
+                        substituted.push('\n');
+                    }
+                    self.execute(&substituted, timeout, true)?;
+                }
+                Step::Teardown(commands) => {
+                    self.execute(&commands, timeout, false)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a debugger script by sending it to the debugger over stdin one step at a time.
+/// The setup of the script, each user specified debugger command and the teardown of the script are sent
+/// separately, and after each the driver waits for the debugger to print a sync marker before sending the next.
+/// The timeout applies to each step, and the remaining debugger commands are skipped once the test executable exits.
+/// References to the named groups of the `pattern:` expected statements in a debugger command, i.e. `${ptr}`,
+/// are replaced with the values captured from the output of the earlier debugger commands.
+/// Returns the output of the debugger without the sync markers.
+pub fn run_session(
+    debugger_type: DebuggerType,
+    mut command: Command,
+    debugger_script: &str,
+    expected_statements: &[&str],
+    timeout: Duration,
+) -> anyhow::Result<String> {
+    let captures = Captures::new(expected_statements)?;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(&['\r', '\n'][..]);
+                    if sender.send(text.to_string()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let mut session = DebuggerSession::new(debugger_type, stdin, receiver, captures);
+    if let Err(error) = session.run(debugger_script, timeout) {
+        let _ = child.kill();
+        let _ = child.wait();
+        anyhow::bail!("{}\n{}", error, session.transcript);
+    }
+
+    // Wait for the debugger to exit.
+    drop(session.stdin);
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                anyhow::bail!(
+                    "{} failed with {}.\n{}",
+                    debugger_type,
+                    status,
+                    session.transcript
+                );
+            }
+            break;
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!(
+                "{} did not exit within {} seconds.\n{}",
+                debugger_type,
+                timeout.as_secs(),
+                session.transcript
+            );
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Ok(session.transcript)
+}
+
+#[test]
+fn test_split_script() {
+    let steps = split_script("run\ncontinue\necho start_debugger_command_0\\n\nprint a\necho end_debugger_command_0\\n\necho start_debugger_command_1\\n\nprint b\necho end_debugger_command_1\\n\ncontinue\nquit\n");
+    assert_eq!(
+        vec![
+            Step::Setup("run\ncontinue\n".to_string()),
+            Step::Command(
+                "echo start_debugger_command_0\\n\nprint a\necho end_debugger_command_0\\n\n"
+                    .to_string()
+            ),
+            Step::Command(
+                "echo start_debugger_command_1\\n\nprint b\necho end_debugger_command_1\\n\n"
+                    .to_string()
+            ),
+            Step::Teardown("continue\nquit\n".to_string()),
+        ],
+        steps
+    );
+
+    assert_eq!(
+        vec![Step::Teardown("run\nquit\n".to_string())],
+        split_script("run\nquit\n")
+    );
+}
+
+#[test]
+fn test_sync_command() {
+    assert_eq!(
+        ".printf \"__debugger_test_sync_%d\\n\", 0n10",
+        sync_command(DebuggerType::Cdb, 10)
+    );
+    assert_eq!(
+        "printf \"__debugger_test_sync_%d\\n\", 1",
+        sync_command(DebuggerType::RustGdb, 1)
+    );
+    assert_eq!(
+        "script print(\"__debugger_test_sync_%d\" % 2)",
+        sync_command(DebuggerType::Lldb, 2)
+    );
+}
+
+#[test]
+fn test_debugger_session() {
+    let (sender, receiver) = mpsc::channel();
+    let output = [
+        "Breakpoint 1, test::__break () at src/test.rs:4",
+        "(gdb) __debugger_test_sync_0",
+        "(gdb) start_debugger_command_0",
+        "$1 = 10",
+        "end_debugger_command_0",
+        "(gdb) __debugger_test_sync_1",
+        "(gdb) start_debugger_command_1",
+        "[Inferior 1 (process 42) exited normally]",
+        "end_debugger_command_1",
+        "(gdb) __debugger_test_sync_2",
+    ];
+    for line in output.iter() {
+        sender.send(line.to_string()).unwrap();
+    }
+    drop(sender);
+
+    let mut session = DebuggerSession::new(
+        DebuggerType::Gdb,
+        Vec::new(),
+        receiver,
+        Captures::new(&[]).unwrap(),
+    );
+    session
+        .run(
+            "run\necho start_debugger_command_0\\n\nprint a\necho end_debugger_command_0\\n\necho start_debugger_command_1\\n\ncontinue\necho end_debugger_command_1\\n\necho start_debugger_command_2\\n\nprint b\necho end_debugger_command_2\\n\ncontinue\nquit\n",
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+    // The last debugger command is skipped since the test executable has exited.
+    assert_eq!(
+        "run\nprintf \"__debugger_test_sync_%d\\n\", 0\necho start_debugger_command_0\\n\nprint a\necho end_debugger_command_0\\n\nprintf \"__debugger_test_sync_%d\\n\", 1\necho start_debugger_command_1\\n\ncontinue\necho end_debugger_command_1\\n\nprintf \"__debugger_test_sync_%d\\n\", 2\ncontinue\nquit\n",
+        String::from_utf8(session.stdin).unwrap()
+    );
+    assert_eq!(
+        "Breakpoint 1, test::__break () at src/test.rs:4\n(gdb) start_debugger_command_0\n$1 = 10\nend_debugger_command_0\n(gdb) start_debugger_command_1\n[Inferior 1 (process 42) exited normally]\nend_debugger_command_1\n",
+        session.transcript
+    );
+}
+
+#[test]
+fn test_debugger_session_timeout() {
+    let (_sender, receiver) = mpsc::channel();
+    let mut session = DebuggerSession::new(
+        DebuggerType::Gdb,
+        Vec::new(),
+        receiver,
+        Captures::new(&[]).unwrap(),
+    );
+    assert_eq!(
+        "The debugger did not finish running `run; continue` within 0 seconds.",
+        session
+            .run("run\ncontinue\n", Duration::from_secs(0))
+            .unwrap_err()
+            .to_string()
+    );
+}
@@ -0,0 +1,123 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// The environment variable with the directory to record debugger transcripts to.
+pub const RECORD_ENV_VAR: &str = "DEBUGGER_TEST_RECORD";
+
+/// The environment variable with the directory to replay recorded debugger transcripts from.
+pub const REPLAY_ENV_VAR: &str = "DEBUGGER_TEST_REPLAY";
+
+/// Get the paths of the recorded debugger script and debugger output for the given test.
+/// The `::` separators of the test name are replaced since `:` is not valid in Windows file names.
+fn transcript_paths(dir: &Path, test_name: &str) -> (PathBuf, PathBuf) {
+    let file_stem = test_name.replace("::", ".");
+    (
+        dir.join(format!("{}.debugger_script", file_stem)),
+        dir.join(format!("{}.debugger_out", file_stem)),
+    )
+}
+
+/// Save the debugger script and the debugger output of a test to the given directory.
+fn write_transcript(
+    dir: &Path,
+    test_name: &str,
+    debugger_script: &str,
+    debugger_output: &str,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let (script_path, output_path) = transcript_paths(dir, test_name);
+    std::fs::write(script_path, debugger_script)?;
+    std::fs::write(output_path, debugger_output)?;
+    Ok(())
+}
+
+/// Read the debugger output recorded for a test from the given directory.
+/// Fails if the recorded debugger script does not match the current debugger script.
+fn read_transcript(dir: &Path, test_name: &str, debugger_script: &str) -> anyhow::Result<String> {
+    let (script_path, output_path) = transcript_paths(dir, test_name);
+    let recorded_script = std::fs::read_to_string(&script_path).map_err(|error| {
+        anyhow::anyhow!(
+            "Failed to read the recorded debugger script `{}`: {}",
+            script_path.display(),
+            error
+        )
+    })?;
+
+    if recorded_script != debugger_script {
+        anyhow::bail!(
+            "The debugger script for `{}` does not match the recorded debugger script `{}`, record the test again with `{}`.\nRecorded:\n{}\nCurrent:\n{}",
+            test_name,
+            script_path.display(),
+            RECORD_ENV_VAR,
+            recorded_script,
+            debugger_script
+        );
+    }
+
+    std::fs::read_to_string(&output_path).map_err(|error| {
+        anyhow::anyhow!(
+            "Failed to read the recorded debugger output `{}`: {}",
+            output_path.display(),
+            error
+        )
+    })
+}
+
+/// Save the debugger script and the debugger output of a test when `DEBUGGER_TEST_RECORD` is set.
+pub fn record_transcript(
+    test_name: &str,
+    debugger_script: &str,
+    debugger_output: &str,
+) -> anyhow::Result<()> {
+    match env::var_os(RECORD_ENV_VAR) {
+        Some(dir) => write_transcript(Path::new(&dir), test_name, debugger_script, debugger_output),
+        None => Ok(()),
+    }
+}
+
+/// Get the recorded debugger output of a test when `DEBUGGER_TEST_REPLAY` is set.
+/// Returns `None` if the test should launch the debugger.
+pub fn replay_transcript(test_name: &str, debugger_script: &str) -> anyhow::Result<Option<String>> {
+    match env::var_os(REPLAY_ENV_VAR) {
+        Some(dir) => read_transcript(Path::new(&dir), test_name, debugger_script).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[test]
+fn test_transcript_paths() {
+    let (script_path, output_path) =
+        transcript_paths(Path::new("transcripts"), "test::test_a__gdb");
+    assert_eq!(
+        Path::new("transcripts").join("test.test_a__gdb.debugger_script"),
+        script_path
+    );
+    assert_eq!(
+        Path::new("transcripts").join("test.test_a__gdb.debugger_out"),
+        output_path
+    );
+}
+
+#[test]
+fn test_record_and_replay_transcript() {
+    let dir = env::temp_dir().join(format!("debugger_test_transcripts_{}", std::process::id()));
+    write_transcript(&dir, "test::test_a__gdb", "info locals\n", "a = 10\n").unwrap();
+
+    assert_eq!(
+        "a = 10\n",
+        read_transcript(&dir, "test::test_a__gdb", "info locals\n").unwrap()
+    );
+
+    let changed_script = read_transcript(&dir, "test::test_a__gdb", "print a\n");
+    assert!(changed_script.unwrap_err().to_string().starts_with(
+        "The debugger script for `test::test_a__gdb` does not match the recorded debugger script"
+    ));
+
+    let missing = read_transcript(&dir, "test::test_b__gdb", "info locals\n");
+    assert!(missing
+        .unwrap_err()
+        .to_string()
+        .starts_with("Failed to read the recorded debugger script"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
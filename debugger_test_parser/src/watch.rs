@@ -0,0 +1,188 @@
+use serde_json::Value;
+
+use crate::json::parse_expected_value;
+
+/// The prefix of the lines printed by the watchpoint helper loaded into the debugger each time a watched expression is written.
+pub const WATCH_PREFIX: &str = "debugger_test_watch: ";
+
+/// A write to a watched expression, recorded by the watchpoint helper loaded into the debugger.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchTrigger {
+    pub expression: String,
+    pub old_value: Value,
+    pub new_value: Value,
+    /// The function which wrote the new value.
+    pub function: Option<String>,
+    /// The source file and line of the write.
+    pub file: Option<String>,
+    pub line: Option<u64>,
+}
+
+impl WatchTrigger {
+    /// Check whether the write happened at the given source file and line.
+    /// The file matches the end of the recorded path, so a path relative to the crate matches the absolute path.
+    fn is_at(&self, file: &str, line: u64) -> bool {
+        match &self.file {
+            Some(trigger_file) => {
+                trigger_file
+                    .replace('\\', "/")
+                    .ends_with(&file.replace('\\', "/"))
+                    && self.line == Some(line)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Parse the watchpoint write printed on a line of debugger output, the debugger prompt can precede it.
+fn parse_trigger(line: &str) -> Option<WatchTrigger> {
+    let index = line.find(WATCH_PREFIX)?;
+    let document: Value = serde_json::from_str(&line[index + WATCH_PREFIX.len()..]).ok()?;
+
+    Some(WatchTrigger {
+        expression: document.get("expression")?.as_str()?.to_string(),
+        old_value: document.get("old").cloned().unwrap_or(Value::Null),
+        new_value: document.get("new").cloned().unwrap_or(Value::Null),
+        function: document
+            .get("function")
+            .and_then(|function| function.as_str())
+            .map(|function| function.to_string()),
+        file: document
+            .get("file")
+            .and_then(|file| file.as_str())
+            .map(|file| file.to_string()),
+        line: document.get("line").and_then(|line| line.as_u64()),
+    })
+}
+
+/// Get the writes to the watched expressions, in the order the test wrote them.
+pub fn watch_triggers(debugger_output: &str) -> Vec<WatchTrigger> {
+    debugger_output.lines().filter_map(parse_trigger).collect()
+}
+
+/// An expected write to a watched expression, i.e. `watch:self.head = 0 -> 1 at src/ring.rs:12`.
+pub(crate) struct WatchExpectation {
+    expression: String,
+    old_value: Value,
+    new_value: Value,
+    location: Option<(String, u64)>,
+}
+
+impl WatchExpectation {
+    /// Parse an expected write of the form `<expression> = <old> -> <new>`, optionally followed by `at <file>:<line>`.
+    /// The values are parsed as JSON, a value which is not valid JSON is expected to be a string.
+    pub(crate) fn parse(expected: &str) -> anyhow::Result<Self> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid watch expectation `{}`, expected `<expression> = <old> -> <new>` or `<expression> = <old> -> <new> at <file>:<line>`.",
+                expected
+            )
+        };
+
+        // The location is only parsed when it ends with a line number, the values can contain ` at `.
+        let mut write = expected;
+        let mut location = None;
+        if let Some(index) = expected.rfind(" at ") {
+            let at = expected[index + 4..].trim();
+            if let Some(separator) = at.rfind(':') {
+                if let Ok(line) = at[separator + 1..].parse::<u64>() {
+                    location = Some((at[..separator].to_string(), line));
+                    write = &expected[..index];
+                }
+            }
+        }
+
+        let assignment = write.find(" = ").ok_or_else(invalid)?;
+        let values = &write[assignment + 3..];
+        let arrow = values.find(" -> ").ok_or_else(invalid)?;
+
+        let expression = write[..assignment].trim();
+        if expression.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(WatchExpectation {
+            expression: expression.to_string(),
+            old_value: parse_expected_value(values[..arrow].trim()),
+            new_value: parse_expected_value(values[arrow + 4..].trim()),
+            location,
+        })
+    }
+
+    /// Check whether the line of debugger output is the expected write.
+    pub(crate) fn is_match(&self, line: &str) -> bool {
+        match parse_trigger(line) {
+            Some(trigger) => {
+                trigger.expression == self.expression
+                    && trigger.old_value == self.old_value
+                    && trigger.new_value == self.new_value
+                    && match &self.location {
+                        Some((file, line)) => trigger.is_at(file, *line),
+                        None => true,
+                    }
+            }
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Display for WatchExpectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} = {} -> {}",
+            self.expression, self.old_value, self.new_value
+        )?;
+        if let Some((file, line)) = &self.location {
+            write!(f, " at {}:{}", file, line)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_watch_triggers() {
+    let output = "(gdb) debugger_test_watch: {\"expression\": \"self.head\", \"file\": \"/src/ring/src/lib.rs\", \"function\": \"ring::Ring::push\", \"line\": 12, \"new\": 1, \"old\": 0}\ndebugger_test_watch: {\"expression\": \"self.head\", \"file\": null, \"function\": null, \"line\": 0, \"new\": 2, \"old\": 1}\n";
+    let triggers = watch_triggers(output);
+    assert_eq!(2, triggers.len());
+    assert_eq!(Value::from(0), triggers[0].old_value);
+    assert_eq!(Value::from(1), triggers[0].new_value);
+    assert_eq!(Some("ring::Ring::push".to_string()), triggers[0].function);
+    assert!(triggers[0].is_at("src/lib.rs", 12));
+    assert!(!triggers[1].is_at("src/lib.rs", 12));
+}
+
+#[test]
+fn test_watch_expectation() {
+    let line = r#"debugger_test_watch: {"expression": "self.head", "file": "C:\\ring\\src\\lib.rs", "function": "ring::Ring::push", "line": 12, "new": 1, "old": 0}"#;
+    assert!(WatchExpectation::parse("self.head = 0 -> 1")
+        .unwrap()
+        .is_match(line));
+    assert!(
+        WatchExpectation::parse("self.head = 0 -> 1 at src/lib.rs:12")
+            .unwrap()
+            .is_match(line)
+    );
+    assert!(
+        !WatchExpectation::parse("self.head = 0 -> 1 at src/lib.rs:13")
+            .unwrap()
+            .is_match(line)
+    );
+    assert!(!WatchExpectation::parse("self.tail = 0 -> 1")
+        .unwrap()
+        .is_match(line));
+    assert_eq!(
+        "self.head = 0 -> 1 at src/lib.rs:12",
+        WatchExpectation::parse("self.head = 0 -> 1 at src/lib.rs:12")
+            .unwrap()
+            .to_string()
+    );
+    assert_eq!(
+        "Invalid watch expectation `self.head = 1`, expected `<expression> = <old> -> <new>` or `<expression> = <old> -> <new> at <file>:<line>`.",
+        WatchExpectation::parse("self.head = 1")
+            .err()
+            .unwrap()
+            .to_string()
+    );
+}
@@ -1,4 +1,4 @@
-use debugger_test_parser::parse;
+use debugger_test_parser::{parse, parse_break_label, parse_setup};
 
 /// Verify that a test failed with a specific error message.
 fn verify_expected_failure(result: anyhow::Result<()>, expected_err_msg: &str) {
@@ -208,3 +208,128 @@ regex parse error:
 error: repetition quantifier expects a valid decimal"#;
     verify_expected_failure(parse(output, expected_contents), expected_err_msg);
 }
+
+/// Test expected values in the JSON captures of the debugger output.
+/// Verify expected content.
+#[test]
+fn test_verify_json_captures() {
+    let output = String::from(
+        r#"
+    debugger_test_json: {"expressions": {"v.len()": 2}, "function": "test::f", "locals": {"a": 1, "v": [1, 2]}}
+    start_debugger_command_0
+    end_debugger_command_0
+    debugger_test_json: {"expressions": {"v.len()": 3}, "function": "test::f", "locals": {"a": 2, "v": [1, 2, 3]}}
+    "#,
+    );
+
+    let expected_contents = vec![
+        "json:locals.a = 1",
+        r#"json:expressions["v.len()"] = 2"#,
+        "json:locals.v[2] = 3",
+    ];
+    parse(output.clone(), expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["json:locals.a = 2", "json:locals.a = 1"];
+    let expected_err_msg = "Unable to find expected content in the debugger output. Found 0 JSON captures with: `locals.a = 1`";
+    verify_expected_failure(parse(output.clone(), expected_contents), expected_err_msg);
+
+    // Any other expectation is searched for after the JSON capture, not within it.
+    let expected_contents = vec!["json:locals.a = 1", "start_debugger_command_0"];
+    parse(output.clone(), expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["json:locals.a = 1", r#"{"a": 1"#];
+    let expected_err_msg =
+        r#"Unable to find expected content in the debugger output. Missing line: `{"a": 1`"#;
+    verify_expected_failure(parse(output, expected_contents), expected_err_msg);
+}
+
+/// Test expected content in the output of the commands run at the breakpoints of a label.
+/// Verify expected content.
+#[test]
+fn test_verify_break_labels() {
+    let output = String::from(
+        r#"
+    start_break_label_after_push
+    v : { len=1 }
+    end_break_label_after_push
+    start_break_label_after_pop
+    v : { len=0 }
+    end_break_label_after_pop
+    start_break_label_after_push
+    v : { len=2 }
+    end_break_label_after_push
+    "#,
+    );
+
+    let expected_contents = vec!["v : { len=1 }", "v : { len=2 }"];
+    parse_break_label(&output, "after_push", expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["v : { len=0 }"];
+    parse_break_label(&output, "after_pop", expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["v : { len=0 }"];
+    let expected_err_msg = "At breakpoint label `after_push`: Unable to find expected content in the debugger output. Missing line: `v : { len=0 }`";
+    verify_expected_failure(
+        parse_break_label(&output, "after_push", expected_contents),
+        expected_err_msg,
+    );
+
+    let expected_contents = vec!["v : { len=0 }"];
+    let expected_err_msg = "The breakpoint label `after_clear` was never hit.";
+    verify_expected_failure(
+        parse_break_label(&output, "after_clear", expected_contents),
+        expected_err_msg,
+    );
+}
+
+/// Test expected content in the output of the setup commands run before the test starts.
+/// Verify expected content.
+#[test]
+fn test_verify_setup() {
+    let output = String::from(
+        r#"
+    0:000> .echo start_setup_command_0
+    start_setup_command_0
+    0:000> .nvload C:\natvis\vec.natvis
+    Successfully loaded visualizers in "C:\natvis\vec.natvis"
+    0:000> .echo end_setup_command_0
+    end_setup_command_0
+    start_debugger_command_0
+    v : { len=1 }
+    end_debugger_command_0
+    "#,
+    );
+
+    let expected_contents = vec!["pattern:Successfully loaded visualizers in .*vec.natvis"];
+    parse_setup(&output, expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["v : { len=1 }"];
+    let expected_err_msg = "In the setup commands: Unable to find expected content in the debugger output. Missing line: `v : { len=1 }`";
+    verify_expected_failure(parse_setup(&output, expected_contents), expected_err_msg);
+}
+
+/// Test expected writes in the watchpoint writes of the debugger output.
+/// Verify expected content.
+#[test]
+fn test_verify_watch_triggers() {
+    let output = String::from(
+        r#"
+    debugger_test_watch: {"expression": "self.head", "file": "/ring/src/lib.rs", "function": "ring::Ring::push", "line": 12, "new": 1, "old": 0}
+    start_debugger_command_0
+    end_debugger_command_0
+    debugger_test_watch: {"expression": "self.head", "file": "/ring/src/lib.rs", "function": "ring::Ring::push", "line": 12, "new": 2, "old": 1}
+    debugger_test_watch: {"expression": "self.head", "file": "/ring/src/lib.rs", "function": "ring::Ring::clear", "line": 20, "new": 0, "old": 2}
+    "#,
+    );
+
+    let expected_contents = vec![
+        "watch:self.head = 0 -> 1 at src/lib.rs:12",
+        "watch:self.head = 1 -> 2",
+        "watch:self.head = 2 -> 0 at src/lib.rs:20",
+    ];
+    parse(output.clone(), expected_contents).expect("able to parse output.");
+
+    let expected_contents = vec!["watch:self.head = 1 -> 2", "watch:self.head = 0 -> 1"];
+    let expected_err_msg = "Unable to find expected content in the debugger output. Found 0 watchpoint writes with: `self.head = 0 -> 1`";
+    verify_expected_failure(parse(output, expected_contents), expected_err_msg);
+}
@@ -0,0 +1,234 @@
+//! A mock debugger used to test the code generated by the `debugger_test` attribute on machines without a debugger.
+//!
+//! The mock debugger reads the cdb script generated for the test, prints the text of the `.echo` markers around each
+//! debugger command and prints the canned output for each debugger command from a fixture file.
+//!
+//! Usage: `debugger_test_mock [--fixture <fixture>] [-pd] [-p <pid>] [-cf <script>] [<executable> <args>...]`
+//!
+//! When an executable is given, it is launched with the given arguments before the script is run, the same as
+//! when cdb launches the test executable. Without a script, the commands are read from stdin one line at a time
+//! until `q` or `qd`, the same as when the test drives cdb in session mode.
+//!
+//! A fixture file contains the output of each debugger command, the command is on a line starting with `> `
+//! and is followed by its output. A command can be listed more than once, each time the command is run the
+//! output of the next entry for the command is printed. Lines starting with `#` before the first command are
+//! ignored. For example:
+//!
+//! ```text
+//! # Output for the `test_debugger_group` test.
+//! > dx a
+//! a = 0n1
+//! > dx a
+//! a = 0n2
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+const COMMAND_PREFIX: &str = "> ";
+const START_MARKER_PREFIX: &str = "start_debugger_command_";
+const END_MARKER_PREFIX: &str = "end_debugger_command_";
+
+struct MockArgs {
+    script: Option<PathBuf>,
+    fixture: Option<PathBuf>,
+    executable: Option<(String, Vec<String>)>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<MockArgs> {
+    let mut script = None;
+    let mut fixture = None;
+    let mut executable = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cf" => script = args.next().map(PathBuf::from),
+            "--fixture" => fixture = args.next().map(PathBuf::from),
+            // The process id is ignored since the mock debugger does not attach to the test process.
+            "-p" => {
+                args.next();
+            }
+            "-pd" => {}
+            _ => {
+                executable = Some((arg, args.collect()));
+                break;
+            }
+        }
+    }
+
+    Ok(MockArgs {
+        script,
+        fixture,
+        executable,
+    })
+}
+
+/// Parse the fixture file into the queue of outputs for each debugger command.
+fn parse_fixture(fixture: &str) -> HashMap<String, VecDeque<String>> {
+    let mut outputs: HashMap<String, VecDeque<String>> = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in fixture.lines() {
+        if let Some(command) = line.strip_prefix(COMMAND_PREFIX) {
+            if let Some((command, output)) = current.take() {
+                outputs.entry(command).or_default().push_back(output);
+            }
+            current = Some((command.trim().to_string(), String::new()));
+        } else if let Some((_, output)) = current.as_mut() {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    if let Some((command, output)) = current.take() {
+        outputs.entry(command).or_default().push_back(output);
+    }
+
+    outputs
+}
+
+/// Runs debugger commands, keeping track of the markers around the user specified debugger commands.
+struct MockDebugger {
+    outputs: HashMap<String, VecDeque<String>>,
+    current_command: Option<String>,
+}
+
+impl MockDebugger {
+    fn new(outputs: HashMap<String, VecDeque<String>>) -> Self {
+        MockDebugger {
+            outputs,
+            current_command: None,
+        }
+    }
+
+    /// Run a line of the debugger script, returning the text of a marker or the output of a debugger command.
+    fn run_line(&mut self, line: &str) -> anyhow::Result<String> {
+        let line = line.trim();
+        if let Some(marker) = line.strip_prefix(".echo ") {
+            if let Some(index) = marker.strip_prefix(START_MARKER_PREFIX) {
+                if let Some(open) = &self.current_command {
+                    anyhow::bail!(
+                        "Debugger command {} started before debugger command {} ended.",
+                        index,
+                        open
+                    );
+                }
+                self.current_command = Some(index.to_string());
+            } else if let Some(index) = marker.strip_prefix(END_MARKER_PREFIX) {
+                if self.current_command.as_deref() != Some(index) {
+                    anyhow::bail!("Unexpected end of debugger command {}.", index);
+                }
+                self.current_command = None;
+            }
+
+            return Ok(format!("{}\n", marker));
+        }
+
+        // Only the user specified debugger commands between the markers produce output.
+        if self.current_command.is_some() {
+            if let Some(output) = self
+                .outputs
+                .get_mut(line)
+                .and_then(|queue| queue.pop_front())
+            {
+                return Ok(output);
+            }
+        }
+
+        Ok(String::new())
+    }
+
+    fn finish(&self) -> anyhow::Result<()> {
+        match &self.current_command {
+            Some(open) => anyhow::bail!("Debugger command {} was never ended.", open),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run the debugger script, printing the markers and the output of each debugger command.
+fn run_script(script: &str, outputs: HashMap<String, VecDeque<String>>) -> anyhow::Result<String> {
+    let mut debugger = MockDebugger::new(outputs);
+    let mut stdout = String::new();
+    for line in script.lines() {
+        stdout.push_str(&debugger.run_line(line)?);
+    }
+
+    debugger.finish()?;
+    Ok(stdout)
+}
+
+/// Run the debugger commands read from stdin, printing the output of each command as soon as it has run.
+fn run_stdin(outputs: HashMap<String, VecDeque<String>>) -> anyhow::Result<()> {
+    let mut debugger = MockDebugger::new(outputs);
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim() == "q" || line.trim() == "qd" {
+            break;
+        }
+
+        stdout.write_all(debugger.run_line(&line)?.as_bytes())?;
+        stdout.flush()?;
+    }
+
+    debugger.finish()
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+
+    let outputs = match &args.fixture {
+        Some(fixture) => parse_fixture(&std::fs::read_to_string(fixture)?),
+        None => HashMap::new(),
+    };
+
+    // Run the launched executable to completion, the mock debugger does not stop at breakpoints.
+    if let Some((executable, executable_args)) = &args.executable {
+        let status = Command::new(executable).args(executable_args).status()?;
+        if !status.success() {
+            eprintln!("`{}` exited with {}.", executable, status);
+        }
+    }
+
+    match &args.script {
+        Some(script) => {
+            let script = std::fs::read_to_string(script)?;
+            print!("{}", run_script(&script, outputs)?);
+            Ok(())
+        }
+        None => run_stdin(outputs),
+    }
+}
+
+#[test]
+fn test_parse_fixture() {
+    let outputs = parse_fixture("# comment\n> dx a\na = 0n1\n> dx b\nb = 0n2\n> dx a\na = 0n3\n");
+    assert_eq!(
+        vec!["a = 0n1\n", "a = 0n3\n"],
+        outputs["dx a"].iter().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["b = 0n2\n"],
+        outputs["dx b"].iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_run_script() {
+    let outputs = parse_fixture("> dx a\na = 0n1\n> dx a\na = 0n2\n");
+    let script = "bm *!*::test \"bp /1 @$ra \\\"qd\\\" \"\ng\n.echo start_debugger_command_0\ndx a\n.echo end_debugger_command_0\n.echo start_debugger_command_1\ng\n.echo end_debugger_command_1\n.echo start_debugger_command_2\ndx a\n.echo end_debugger_command_2\nqd\n";
+    assert_eq!(
+        "start_debugger_command_0\na = 0n1\nend_debugger_command_0\nstart_debugger_command_1\nend_debugger_command_1\nstart_debugger_command_2\na = 0n2\nend_debugger_command_2\n",
+        run_script(script, outputs).unwrap()
+    );
+
+    let unterminated = run_script(".echo start_debugger_command_0\ndx a\n", HashMap::new());
+    assert_eq!(
+        "Debugger command 0 was never ended.",
+        unterminated.unwrap_err().to_string()
+    );
+}
@@ -1,37 +1,787 @@
-pub fn create_debugger_script(fn_name: &String, debugger_commands: &Vec<&str>) -> String {
-    let mut debugger_script = String::new();
+use std::str::FromStr;
+
+use debugger_test_parser::{break_label_function, BreakpointLocation};
+
+/// How the debugger is connected to the process running the test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebuggerMode {
+    /// The debugger attaches to the running test process.
+    Attach,
+    /// The debugger launches the test executable to run the test.
+    Launch,
+    /// The debugger launches the test executable and the test sends it one command at a time over stdin.
+    Session,
+}
+
+impl FromStr for DebuggerMode {
+    type Err = anyhow::Error;
+
+    /// Attempts to parse a string into a DebuggerMode
+    fn from_str(s: &str) -> Result<DebuggerMode, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "attach" => Ok(DebuggerMode::Attach),
+            "launch" => Ok(DebuggerMode::Launch),
+            "session" => Ok(DebuggerMode::Session),
+            _ => anyhow::bail!("Invalid debugger mode option: `{}`.", s),
+        }
+    }
+}
+
+/// How the values of the test are captured at each breakpoint, in addition to the output of the debugger commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// A Python helper loaded into the debugger prints the locals of the test as a JSON document.
+    Json,
+}
+
+impl FromStr for CaptureMode {
+    type Err = anyhow::Error;
+
+    /// Attempts to parse a string into a CaptureMode
+    fn from_str(s: &str) -> Result<CaptureMode, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(CaptureMode::Json),
+            _ => anyhow::bail!("Invalid capture option: `{}`.", s),
+        }
+    }
+}
+
+/// A breakpoint set in addition to the `__break` function and the options of the breakpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub location: BreakpointLocation,
+    /// The condition which has to be true for the debugger to stop at the breakpoint, i.e. `i == 42`.
+    pub condition: Option<String>,
+    /// The number of the hit of the breakpoint at which the debugger first stops, i.e. `1000`.
+    pub hit_count: Option<u64>,
+}
+
+impl From<BreakpointLocation> for Breakpoint {
+    fn from(location: BreakpointLocation) -> Self {
+        Breakpoint {
+            location,
+            condition: None,
+            hit_count: None,
+        }
+    }
+}
+
+/// The debugger commands run each time a `break_here!` call with the label is hit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakLabel {
+    pub label: String,
+    pub commands: Vec<String>,
+    pub expected_statements: Vec<String>,
+}
+
+impl BreakLabel {
+    /// Get the name of the function called by `break_here!` for the label.
+    fn function(&self) -> String {
+        break_label_function(&self.label).expect("breakpoint labels are validated when parsed")
+    }
+}
+
+/// The Python helper which prints the locals of a frame and the values of expressions as JSON.
+const JSON_CAPTURE_HELPER: &str = include_str!("json_capture.py");
+
+/// Quote a string as a single line Python string literal.
+fn python_string(value: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Get the Python statement which loads the JSON capture helper.
+/// The helper is loaded on a single line, which both gdb's `python` and lldb's `script` commands accept.
+fn json_capture_helper() -> String {
+    format!("exec({})", python_string(JSON_CAPTURE_HELPER))
+}
+
+/// Get the Python call which prints the JSON capture of the given frame, `1` is the caller of the `__break` function.
+fn json_capture_call(frame_index: usize, expressions: &[&str]) -> String {
+    let expressions = expressions
+        .iter()
+        .map(|expression| python_string(expression))
+        .collect::<Vec<String>>();
+    format!(
+        "debugger_test_capture({}, [{}])",
+        frame_index,
+        expressions.join(", ")
+    )
+}
+
+/// Quote a string with double quotes, the same as lldb and GDB/MI expect for arguments with spaces.
+fn quoted_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Get the option of lldb's `breakpoint command add` which runs the given command when the breakpoint is hit.
+fn lldb_command_option(debugger_command: &str) -> String {
+    format!("-o {}", quoted_string(debugger_command))
+}
+
+/// Where a breakpoint of the debugger script is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BreakpointTarget {
+    /// A function with the given name in any module, i.e. the test function or `__break`.
+    Function(String),
+    /// The function called by `break_here!` for a label, which every function calling `break_here!` defines.
+    Label(String),
+    /// A location of the `breakpoints` option.
+    Location(BreakpointLocation),
+}
+
+/// A breakpoint of the debugger script and the commands run each time it is hit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakpointCommand {
+    pub target: BreakpointTarget,
+    pub condition: Option<String>,
+    pub hit_count: Option<u64>,
+    /// The commands run each time the breakpoint is hit, a trailing `Continue` continues the test.
+    pub commands: Vec<ScriptCommand>,
+}
+
+impl BreakpointCommand {
+    fn new(target: BreakpointTarget, commands: Vec<ScriptCommand>) -> Self {
+        BreakpointCommand {
+            target,
+            condition: None,
+            hit_count: None,
+            commands,
+        }
+    }
+
+    /// Get the number of hits of the breakpoint which the debugger ignores before it stops.
+    fn ignore_count(&self) -> Option<u64> {
+        self.hit_count.map(|hit_count| hit_count - 1)
+    }
+}
+
+/// A command of a debugger script, which each debugger renders in its own syntax.
+/// Commands a debugger does not need, i.e. `DisablePrompts` for cdb, render to no lines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptCommand {
+    /// Disable the paging and confirmation prompts, which would wait for input in batch mode.
+    DisablePrompts,
+    /// Wait for the test to stop after each command, since lldb runs asynchronously when reading from stdin.
+    WaitForStops,
+    /// Evaluate a Python statement with the Python support of gdb or lldb.
+    EvaluatePython(String),
+    /// Load a visualizer file, i.e. a natvis file for cdb, a pretty printer for gdb or a formatter for lldb.
+    LoadVisualizer(String),
+    /// Set a breakpoint.
+    SetBreakpoint(BreakpointCommand),
+    /// Set a watchpoint for the expression with the JSON capture helper, each write is printed and the test continues.
+    Watch { index: usize, expression: String },
+    /// Run the test executable.
+    Run,
+    /// Continue the test to the next breakpoint.
+    Continue,
+    /// Step out of the current function.
+    StepOut,
+    /// Select the caller of the current function.
+    SelectCaller,
+    /// List the breakpoints, which shows whether each of them was resolved.
+    ListBreakpoints,
+    /// Print a marker line, i.e. `start_debugger_command_0`.
+    EchoMarker(String),
+    /// Detach from the test process and quit the debugger when the test function returns.
+    DetachOnReturn,
+    /// Detach from the test process and quit the debugger.
+    Detach,
+    /// Quit the debugger.
+    Quit,
+    /// A command of the user which is passed to the debugger as is.
+    Raw(String),
+}
+
+/// Renders the commands of a debugger script in the syntax of a debugger.
+pub trait ScriptRenderer {
+    /// Render a command as the lines of the debugger script.
+    fn render(&self, command: &ScriptCommand) -> Vec<String>;
+
+    /// Whether `DetachOnReturn` is a command of the test function breakpoint, which cdb runs when the test
+    /// function is hit. Otherwise, it follows the first stop in the test function.
+    fn detaches_from_test_breakpoint(&self) -> bool {
+        false
+    }
+
+    /// Render the commands as a debugger script.
+    fn render_script(&self, commands: &[ScriptCommand]) -> String {
+        let mut debugger_script = String::new();
+        for line in commands.iter().flat_map(|command| self.render(command)) {
+            debugger_script.push_str(format!("{}\n", line).as_str());
+        }
+
+        debugger_script
+    }
+}
+
+/// Renders debugger scripts for cdb, which the mock debugger also runs.
+pub struct CdbRenderer;
+
+impl CdbRenderer {
+    /// Get the command string of a breakpoint, the commands are run one after the other.
+    /// A condition which is false continues with `gc`, so it does not change how the test was resumed.
+    fn breakpoint_commands(&self, breakpoint: &BreakpointCommand) -> String {
+        let commands = breakpoint
+            .commands
+            .iter()
+            .flat_map(|command| self.render(command))
+            .collect::<Vec<String>>()
+            .join("; ");
+        let commands = match &breakpoint.condition {
+            Some(condition) => format!("j @@c++({}) '{}'; 'gc'", condition, commands),
+            None => commands,
+        };
+        if commands.is_empty() {
+            return commands;
+        }
+
+        // A space keeps an escaped quote at the end of the commands apart from the closing quote.
+        let commands = commands.replace('"', "\\\"");
+        let separator = if commands.ends_with('"') { " " } else { "" };
+        format!(" \"{}{}\"", commands, separator)
+    }
+}
+
+impl ScriptRenderer for CdbRenderer {
+    fn detaches_from_test_breakpoint(&self) -> bool {
+        true
+    }
+
+    fn render(&self, command: &ScriptCommand) -> Vec<String> {
+        let line = match command {
+            ScriptCommand::DisablePrompts | ScriptCommand::WaitForStops => return vec![],
+            // cdb has no Python support, so the commands which need it render to no lines.
+            ScriptCommand::EvaluatePython(_) | ScriptCommand::Watch { .. } => return vec![],
+            ScriptCommand::LoadVisualizer(visualizer) => format!(".nvload {}", visualizer),
+            ScriptCommand::SetBreakpoint(breakpoint) => {
+                let target = match &breakpoint.target {
+                    BreakpointTarget::Function(function) | BreakpointTarget::Label(function) => {
+                        format!("bm *!*::{}", function)
+                    }
+                    BreakpointTarget::Location(BreakpointLocation::Line { file, line }) => {
+                        format!("bp `{}:{}`", file.replace('/', "\\"), line)
+                    }
+                    BreakpointTarget::Location(BreakpointLocation::Function(function)) => {
+                        format!("bm *!{}", function)
+                    }
+                };
+                // The hit count is the number of passes of the breakpoint.
+                let passes = match breakpoint.hit_count {
+                    Some(hit_count) => format!(" {}", hit_count),
+                    None => String::new(),
+                };
+                format!(
+                    "{}{}{}",
+                    target,
+                    passes,
+                    self.breakpoint_commands(breakpoint)
+                )
+            }
+            ScriptCommand::Run | ScriptCommand::Continue => String::from("g"),
+            ScriptCommand::StepOut => String::from("gu"),
+            ScriptCommand::SelectCaller => String::from(".frame 1"),
+            ScriptCommand::ListBreakpoints => String::from("bl"),
+            ScriptCommand::EchoMarker(marker) => format!(".echo {}", marker),
+            ScriptCommand::DetachOnReturn => String::from("bp /1 @$ra \"qd\""),
+            ScriptCommand::Detach => String::from("qd"),
+            ScriptCommand::Quit => String::from("q"),
+            ScriptCommand::Raw(debugger_command) => debugger_command.clone(),
+        };
+
+        vec![line]
+    }
+}
+
+/// Renders debugger scripts for gdb and rust-gdb.
+pub struct GdbRenderer;
+
+impl ScriptRenderer for GdbRenderer {
+    fn render(&self, command: &ScriptCommand) -> Vec<String> {
+        match command {
+            ScriptCommand::DisablePrompts => vec![
+                String::from("set pagination off"),
+                String::from("set confirm off"),
+            ],
+            ScriptCommand::WaitForStops => vec![],
+            ScriptCommand::EvaluatePython(statement) => vec![format!("python {}", statement)],
+            ScriptCommand::LoadVisualizer(visualizer) => vec![format!("source {}", visualizer)],
+            ScriptCommand::SetBreakpoint(breakpoint) => {
+                // `break` sets a single breakpoint for every function of a label, so the commands apply to all of them.
+                let mut lines = vec![match &breakpoint.target {
+                    BreakpointTarget::Function(function) => format!("rbreak ::{}$", function),
+                    BreakpointTarget::Label(function) => format!("break {}", function),
+                    BreakpointTarget::Location(location) => format!("break {}", location),
+                }];
+                if let Some(condition) = &breakpoint.condition {
+                    lines.push(format!("condition $bpnum {}", condition));
+                }
+                if let Some(ignore_count) = breakpoint.ignore_count() {
+                    lines.push(format!("ignore $bpnum {}", ignore_count));
+                }
+                if !breakpoint.commands.is_empty() {
+                    lines.push(String::from("commands"));
+                    lines.extend(breakpoint.commands.iter().flat_map(|c| self.render(c)));
+                    lines.push(String::from("end"));
+                }
+                lines
+            }
+            ScriptCommand::Watch { index, expression } => {
+                // The helper sets the watchpoint, the commands print each write and continue the test.
+                let mut lines = self.render(&ScriptCommand::EvaluatePython(format!(
+                    "debugger_test_watch({}, {})",
+                    index,
+                    python_string(expression)
+                )));
+                lines.push(String::from("commands"));
+                lines.push(String::from("silent"));
+                lines.extend(self.render(&ScriptCommand::EvaluatePython(format!(
+                    "debugger_test_watch_hit({})",
+                    index
+                ))));
+                lines.extend(self.render(&ScriptCommand::Continue));
+                lines.push(String::from("end"));
+                lines
+            }
+            ScriptCommand::Run => vec![String::from("run")],
+            ScriptCommand::Continue => vec![String::from("continue")],
+            ScriptCommand::StepOut => vec![String::from("finish")],
+            ScriptCommand::SelectCaller => vec![String::from("up-silently")],
+            ScriptCommand::ListBreakpoints => vec![String::from("info breakpoints")],
+            ScriptCommand::EchoMarker(marker) => vec![format!("echo {}\\n", marker)],
+            ScriptCommand::DetachOnReturn => {
+                let mut lines = self.render(&ScriptCommand::SelectCaller);
+                lines.push(String::from("tbreak *$pc"));
+                lines.push(String::from("commands"));
+                lines.extend(self.render(&ScriptCommand::Detach));
+                lines.push(String::from("end"));
+                lines.push(String::from("down-silently"));
+                lines
+            }
+            ScriptCommand::Detach => vec![String::from("detach"), String::from("quit")],
+            ScriptCommand::Quit => vec![String::from("quit")],
+            ScriptCommand::Raw(debugger_command) => vec![debugger_command.clone()],
+        }
+    }
+}
+
+/// Renders debugger scripts for lldb and rust-lldb.
+pub struct LldbRenderer;
+
+impl ScriptRenderer for LldbRenderer {
+    fn render(&self, command: &ScriptCommand) -> Vec<String> {
+        match command {
+            ScriptCommand::DisablePrompts => vec![],
+            ScriptCommand::WaitForStops => {
+                vec![String::from("script lldb.debugger.SetAsync(False)")]
+            }
+            ScriptCommand::EvaluatePython(statement) => vec![format!("script {}", statement)],
+            // Python formatters are imported as modules, any other file is a script of lldb commands.
+            ScriptCommand::LoadVisualizer(visualizer) if visualizer.ends_with(".py") => {
+                vec![format!(
+                    "command script import {}",
+                    quoted_string(visualizer)
+                )]
+            }
+            ScriptCommand::LoadVisualizer(visualizer) => {
+                vec![format!("command source {}", quoted_string(visualizer))]
+            }
+            ScriptCommand::SetBreakpoint(breakpoint) => {
+                let mut breakpoint_set = match &breakpoint.target {
+                    BreakpointTarget::Function(function) => {
+                        format!("breakpoint set -n {}", function)
+                    }
+                    BreakpointTarget::Label(function)
+                    | BreakpointTarget::Location(BreakpointLocation::Function(function)) => {
+                        format!("breakpoint set --name {}", function)
+                    }
+                    BreakpointTarget::Location(BreakpointLocation::Line { file, line }) => {
+                        format!("breakpoint set --file {} --line {}", file, line)
+                    }
+                };
+                if let Some(condition) = &breakpoint.condition {
+                    breakpoint_set
+                        .push_str(format!(" --condition {}", quoted_string(condition)).as_str());
+                }
+                if let Some(ignore_count) = breakpoint.ignore_count() {
+                    breakpoint_set.push_str(format!(" --ignore-count {}", ignore_count).as_str());
+                }
+
+                // lldb continues after the commands of the breakpoint with `--auto-continue` instead of `continue`.
+                let mut commands = breakpoint.commands.as_slice();
+                if let Some((ScriptCommand::Continue, rest)) = commands.split_last() {
+                    breakpoint_set.push_str(" --auto-continue true");
+                    commands = rest;
+                }
+
+                let mut lines = vec![breakpoint_set];
+                if !commands.is_empty() {
+                    let options = commands
+                        .iter()
+                        .flat_map(|command| self.render(command))
+                        .map(|line| lldb_command_option(&line))
+                        .collect::<Vec<String>>();
+                    lines.push(format!("breakpoint command add {}", options.join(" ")));
+                }
+                lines
+            }
+            // The helper also adds the callback which prints each write and continues the test.
+            ScriptCommand::Watch { index, expression } => {
+                self.render(&ScriptCommand::EvaluatePython(format!(
+                    "debugger_test_watch({}, {})",
+                    index,
+                    python_string(expression)
+                )))
+            }
+            ScriptCommand::Run => vec![String::from("process launch")],
+            ScriptCommand::Continue => vec![String::from("continue")],
+            ScriptCommand::StepOut => vec![String::from("thread step-out")],
+            ScriptCommand::SelectCaller => vec![String::from("frame select 1")],
+            ScriptCommand::ListBreakpoints => vec![String::from("breakpoint list")],
+            ScriptCommand::EchoMarker(marker) => vec![format!("script print(\"{}\")", marker)],
+            // The debugger quits at the end of the script, after the process is detached.
+            ScriptCommand::DetachOnReturn => {
+                let mut lines = self.render(&ScriptCommand::SelectCaller);
+                lines.push(String::from("breakpoint set --one-shot true --address $pc"));
+                lines.push(format!(
+                    "breakpoint command add {}",
+                    lldb_command_option("process detach")
+                ));
+                lines.push(String::from("frame select 0"));
+                lines
+            }
+            ScriptCommand::Detach => vec![String::from("process detach"), String::from("quit")],
+            ScriptCommand::Quit => vec![String::from("quit")],
+            ScriptCommand::Raw(debugger_command) => vec![debugger_command.clone()],
+        }
+    }
+}
+
+/// The options of a `debugger_test` section which the cdb, gdb and lldb scripts are created from.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptOptions<'a> {
+    pub fn_name: &'a str,
+    pub debugger_commands: &'a [&'a str],
+    pub mode: DebuggerMode,
+    /// The visualizer file loaded before the setup commands, i.e. a natvis file for cdb.
+    pub visualizer: Option<&'a str>,
+    /// The commands run before the breakpoints are set and the test starts.
+    pub setup_commands: &'a [&'a str],
+    pub breakpoints: &'a [Breakpoint],
+    /// The expressions captured by the JSON capture helper, `None` when the values are not captured.
+    pub json_capture: Option<&'a [&'a str]>,
+    pub break_labels: &'a [BreakLabel],
+    pub watch_expressions: &'a [&'a str],
+}
+
+impl<'a> ScriptOptions<'a> {
+    pub fn new(fn_name: &'a str, debugger_commands: &'a [&'a str], mode: DebuggerMode) -> Self {
+        ScriptOptions {
+            fn_name,
+            debugger_commands,
+            mode,
+            visualizer: None,
+            setup_commands: &[],
+            breakpoints: &[],
+            json_capture: None,
+            break_labels: &[],
+            watch_expressions: &[],
+        }
+    }
+}
+
+/// Get the breakpoint command for a breakpoint of the `breakpoints` option.
+fn location_breakpoint(breakpoint: &Breakpoint, commands: Vec<ScriptCommand>) -> BreakpointCommand {
+    BreakpointCommand {
+        target: BreakpointTarget::Location(breakpoint.location.clone()),
+        condition: breakpoint.condition.clone(),
+        hit_count: breakpoint.hit_count,
+        commands,
+    }
+}
+
+/// Get the user specified debugger commands, each between the markers which separate its output.
+fn debugger_command_markers(debugger_commands: &[&str]) -> Vec<ScriptCommand> {
+    let mut commands = Vec::new();
+    for (i, debugger_command) in debugger_commands.iter().enumerate() {
+        commands.push(ScriptCommand::EchoMarker(format!(
+            "start_debugger_command_{}",
+            i
+        )));
+        commands.push(ScriptCommand::Raw(debugger_command.to_string()));
+        commands.push(ScriptCommand::EchoMarker(format!(
+            "end_debugger_command_{}",
+            i
+        )));
+    }
+
+    commands
+}
+
+/// Get the commands of the debugger script for a `debugger_test` section.
+pub fn script_commands(
+    renderer: &dyn ScriptRenderer,
+    options: &ScriptOptions,
+) -> Vec<ScriptCommand> {
+    let mut commands = vec![ScriptCommand::DisablePrompts];
+    if options.mode == DebuggerMode::Session {
+        commands.push(ScriptCommand::WaitForStops);
+    }
+
+    // Load the JSON capture helper, which also sets the watchpoints.
+    if options.json_capture.is_some() || !options.watch_expressions.is_empty() {
+        commands.push(ScriptCommand::EvaluatePython(json_capture_helper()));
+    }
+
+    if let Some(visualizer) = options.visualizer {
+        commands.push(ScriptCommand::LoadVisualizer(visualizer.to_string()));
+    }
+
+    // Run the setup commands before the test starts, i.e. to load visualizers.
+    for (i, setup_command) in options.setup_commands.iter().enumerate() {
+        commands.push(ScriptCommand::EchoMarker(format!(
+            "start_setup_command_{}",
+            i
+        )));
+        commands.push(ScriptCommand::Raw(setup_command.to_string()));
+        commands.push(ScriptCommand::EchoMarker(format!(
+            "end_setup_command_{}",
+            i
+        )));
+    }
 
     // Add an inital breakpoint for the test function.
-    // Also add a breakpoint at the end of the test function which quits the debugger.
-    debugger_script.push_str(format!("bm *!*::{} \"bp /1 @$ra \\\"qd\\\" \"\n", fn_name).as_str());
+    // When attached, also add a breakpoint at the end of the test function which detaches the debugger.
+    let detach_on_return = options.mode == DebuggerMode::Attach;
+    let test_commands = if detach_on_return && renderer.detaches_from_test_breakpoint() {
+        vec![ScriptCommand::DetachOnReturn]
+    } else {
+        vec![]
+    };
+    commands.push(ScriptCommand::SetBreakpoint(BreakpointCommand::new(
+        BreakpointTarget::Function(options.fn_name.to_string()),
+        test_commands,
+    )));
+
+    // Add the user specified breakpoints and step out of the `__break` function.
+    // The values of the test are captured first, since gdb and lldb do not run the commands after stepping out.
+    let mut break_commands = Vec::new();
+    if let Some(expressions) = options.json_capture {
+        break_commands.push(ScriptCommand::EvaluatePython(json_capture_call(
+            1,
+            expressions,
+        )));
+    }
+    break_commands.push(ScriptCommand::StepOut);
+    commands.push(ScriptCommand::SetBreakpoint(BreakpointCommand::new(
+        BreakpointTarget::Function(String::from("__break")),
+        break_commands,
+    )));
+    for breakpoint in options.breakpoints {
+        let breakpoint_commands = match options.json_capture {
+            Some(expressions) => vec![ScriptCommand::EvaluatePython(json_capture_call(
+                0,
+                expressions,
+            ))],
+            None => vec![],
+        };
+        commands.push(ScriptCommand::SetBreakpoint(location_breakpoint(
+            breakpoint,
+            breakpoint_commands,
+        )));
+    }
 
-    // Add the user specified breakpoints.
-    debugger_script.push_str("bm *!*::__break \"gu\"\n");
+    // Run the commands of each breakpoint label in the caller of the `break_here!` function and continue.
+    for break_label in options.break_labels {
+        let mut label_commands = vec![
+            ScriptCommand::SelectCaller,
+            ScriptCommand::EchoMarker(format!("start_break_label_{}", break_label.label)),
+        ];
+        label_commands.extend(break_label.commands.iter().cloned().map(ScriptCommand::Raw));
+        label_commands.push(ScriptCommand::EchoMarker(format!(
+            "end_break_label_{}",
+            break_label.label
+        )));
+        label_commands.push(ScriptCommand::Continue);
+        commands.push(ScriptCommand::SetBreakpoint(BreakpointCommand::new(
+            BreakpointTarget::Label(break_label.function()),
+            label_commands,
+        )));
+    }
 
     // Run the debugger to the start of the test.
-    debugger_script.push_str("g\n");
-    debugger_script.push_str("bl\n");
+    match options.mode {
+        DebuggerMode::Attach => commands.push(ScriptCommand::Continue),
+        DebuggerMode::Launch | DebuggerMode::Session => commands.push(ScriptCommand::Run),
+    }
+    commands.push(ScriptCommand::ListBreakpoints);
+
+    // Otherwise, add the breakpoint at the return address of the test function now that it is hit.
+    if detach_on_return && !renderer.detaches_from_test_breakpoint() {
+        commands.push(ScriptCommand::DetachOnReturn);
+    }
 
     // Run the debugger to the first user set breakpoint.
-    debugger_script.push_str("g\n");
+    commands.push(ScriptCommand::Continue);
 
-    for (i, debugger_comamand) in debugger_commands.iter().enumerate() {
-        debugger_script.push_str(format!(".echo start_debugger_command_{}\n", i).as_str());
-        debugger_script.push_str(format!("{}\n", debugger_comamand).as_str());
-        debugger_script.push_str(format!(".echo end_debugger_command_{}\n", i).as_str());
+    // Set the watchpoints at the first stop.
+    for (index, expression) in options.watch_expressions.iter().enumerate() {
+        commands.push(ScriptCommand::Watch {
+            index,
+            expression: expression.to_string(),
+        });
     }
 
-    // Quit and detach the debugger
-    debugger_script.push_str("qd\n");
+    commands.extend(debugger_command_markers(options.debugger_commands));
 
-    debugger_script
+    match options.mode {
+        // Detach and quit the debugger
+        DebuggerMode::Attach => commands.push(ScriptCommand::Detach),
+        // Run the test to completion and quit the debugger
+        DebuggerMode::Launch | DebuggerMode::Session => {
+            commands.push(ScriptCommand::Continue);
+            commands.push(ScriptCommand::Quit);
+        }
+    }
+
+    commands
+}
+
+/// Create the debugger script for a `debugger_test` section with the renderer of cdb, gdb or lldb.
+pub fn create_debugger_script(renderer: &dyn ScriptRenderer, options: &ScriptOptions) -> String {
+    renderer.render_script(&script_commands(renderer, options))
+}
+
+/// Get the GDB/MI command which runs the given gdb command, commands starting with `-` are GDB/MI commands.
+fn gdb_mi_command(debugger_command: &str) -> String {
+    if debugger_command.starts_with('-') {
+        return debugger_command.to_string();
+    }
+
+    format!(
+        "-interpreter-exec console {}",
+        quoted_string(debugger_command)
+    )
+}
+
+/// Renders debugger scripts for the GDB/MI driver, which sends one line of the script at a time.
+/// The driver steps out of `__break` itself, so the commands of a breakpoint are not rendered.
+pub struct GdbMiRenderer;
+
+impl ScriptRenderer for GdbMiRenderer {
+    fn render(&self, command: &ScriptCommand) -> Vec<String> {
+        match command {
+            ScriptCommand::DisablePrompts => vec![String::from("-gdb-set confirm off")],
+            ScriptCommand::SetBreakpoint(breakpoint) => {
+                let mut break_insert = String::from("-break-insert -f");
+                if let Some(condition) = &breakpoint.condition {
+                    break_insert.push_str(format!(" -c {}", quoted_string(condition)).as_str());
+                }
+                if let Some(ignore_count) = breakpoint.ignore_count() {
+                    break_insert.push_str(format!(" -i {}", ignore_count).as_str());
+                }
+                let location = match &breakpoint.target {
+                    BreakpointTarget::Function(function) | BreakpointTarget::Label(function) => {
+                        function.clone()
+                    }
+                    BreakpointTarget::Location(location) => location.to_string(),
+                };
+                vec![format!("{} {}", break_insert, location)]
+            }
+            // Watchpoints and the breakpoint at the return address need breakpoint commands.
+            ScriptCommand::Watch { .. } | ScriptCommand::DetachOnReturn => vec![],
+            ScriptCommand::Run => vec![String::from("-exec-run")],
+            ScriptCommand::Continue => vec![String::from("-exec-continue")],
+            ScriptCommand::StepOut => vec![String::from("-exec-finish")],
+            ScriptCommand::ListBreakpoints => vec![String::from("-break-list")],
+            ScriptCommand::Detach => {
+                vec![String::from("-target-detach"), String::from("-gdb-exit")]
+            }
+            ScriptCommand::Quit => vec![String::from("-gdb-exit")],
+            // Any other command is run as a gdb console command.
+            _ => GdbRenderer
+                .render(command)
+                .iter()
+                .map(|line| gdb_mi_command(line))
+                .collect(),
+        }
+    }
+}
+
+/// Renders debugger scripts for the DAP drivers, which run each line of the script with the matching request.
+/// The drivers set the breakpoints and run the test themselves, so only the user commands are rendered.
+pub struct DapRenderer;
+
+impl ScriptRenderer for DapRenderer {
+    fn render(&self, command: &ScriptCommand) -> Vec<String> {
+        match command {
+            ScriptCommand::Raw(debugger_command) => vec![debugger_command.clone()],
+            _ => vec![],
+        }
+    }
+}
+
+/// Create the script for the GDB/MI driver, which always launches the test executable.
+pub fn create_gdb_mi_debugger_script(
+    debugger_commands: &[&str],
+    breakpoints: &[Breakpoint],
+) -> String {
+    // Keep the output of the test executable out of the GDB/MI output.
+    let mut commands = vec![
+        ScriptCommand::DisablePrompts,
+        ScriptCommand::Raw(String::from("-inferior-tty-set /dev/null")),
+        ScriptCommand::SetBreakpoint(BreakpointCommand::new(
+            BreakpointTarget::Function(String::from("__break")),
+            vec![],
+        )),
+    ];
+    commands.extend(
+        breakpoints.iter().map(|breakpoint| {
+            ScriptCommand::SetBreakpoint(location_breakpoint(breakpoint, vec![]))
+        }),
+    );
+
+    // Run the debugger to the first user set breakpoint.
+    commands.push(ScriptCommand::Run);
+    commands.extend(debugger_command_markers(debugger_commands));
+
+    // Run the test to completion and quit the debugger
+    commands.push(ScriptCommand::Continue);
+    commands.push(ScriptCommand::Quit);
+
+    GdbMiRenderer.render_script(&commands)
+}
+
+/// Create the script for the DAP drivers.
+pub fn create_dap_debugger_script(debugger_commands: &[&str]) -> String {
+    let commands = debugger_commands
+        .iter()
+        .map(|debugger_command| ScriptCommand::Raw(debugger_command.to_string()))
+        .collect::<Vec<ScriptCommand>>();
+
+    DapRenderer.render_script(&commands)
 }
 
 #[test]
 fn test_debugger_script_empty() {
     let test_name = String::from("test1");
     let debugger_commands = vec![];
-    let debugger_script = create_debugger_script(&test_name, &debugger_commands);
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions::new(&test_name, &debugger_commands, DebuggerMode::Attach),
+    );
     let expected = r#"bm *!*::test1 "bp /1 @$ra \"qd\" "
 bm *!*::__break "gu"
 g
@@ -47,7 +797,10 @@ qd
 fn test_debugger_script() {
     let test_name = String::from("test1");
     let debugger_commands = vec!["dv", "g", ".nvlist"];
-    let debugger_script = create_debugger_script(&test_name, &debugger_commands);
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions::new(&test_name, &debugger_commands, DebuggerMode::Attach),
+    );
     let expected = r#"bm *!*::test1 "bp /1 @$ra \"qd\" "
 bm *!*::__break "gu"
 g
@@ -67,3 +820,917 @@ qd
 
     assert_eq!(expected.to_string(), debugger_script);
 }
+
+#[test]
+fn test_gdb_debugger_script_empty() {
+    let debugger_commands = vec![];
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Attach),
+    );
+    let expected = r#"set pagination off
+set confirm off
+rbreak ::test1$
+rbreak ::__break$
+commands
+finish
+end
+continue
+info breakpoints
+up-silently
+tbreak *$pc
+commands
+detach
+quit
+end
+down-silently
+continue
+detach
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_gdb_debugger_script() {
+    let debugger_commands = vec!["info locals", "continue", "print a"];
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Attach),
+    );
+    let expected = r#"set pagination off
+set confirm off
+rbreak ::test1$
+rbreak ::__break$
+commands
+finish
+end
+continue
+info breakpoints
+up-silently
+tbreak *$pc
+commands
+detach
+quit
+end
+down-silently
+continue
+echo start_debugger_command_0\n
+info locals
+echo end_debugger_command_0\n
+echo start_debugger_command_1\n
+continue
+echo end_debugger_command_1\n
+echo start_debugger_command_2\n
+print a
+echo end_debugger_command_2\n
+detach
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_lldb_debugger_script_empty() {
+    let debugger_commands = vec![];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Attach),
+    );
+    let expected = r#"breakpoint set -n test1
+breakpoint set -n __break
+breakpoint command add -o "thread step-out"
+continue
+breakpoint list
+frame select 1
+breakpoint set --one-shot true --address $pc
+breakpoint command add -o "process detach"
+frame select 0
+continue
+process detach
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_lldb_debugger_script() {
+    let debugger_commands = vec!["frame variable", "continue", "frame variable a"];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Attach),
+    );
+    let expected = r#"breakpoint set -n test1
+breakpoint set -n __break
+breakpoint command add -o "thread step-out"
+continue
+breakpoint list
+frame select 1
+breakpoint set --one-shot true --address $pc
+breakpoint command add -o "process detach"
+frame select 0
+continue
+script print("start_debugger_command_0")
+frame variable
+script print("end_debugger_command_0")
+script print("start_debugger_command_1")
+continue
+script print("end_debugger_command_1")
+script print("start_debugger_command_2")
+frame variable a
+script print("end_debugger_command_2")
+process detach
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_debugger_script_launch() {
+    let debugger_commands = vec!["dv"];
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch),
+    );
+    let expected = r#"bm *!*::test1
+bm *!*::__break "gu"
+g
+bl
+g
+.echo start_debugger_command_0
+dv
+.echo end_debugger_command_0
+g
+q
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_gdb_debugger_script_launch() {
+    let debugger_commands = vec!["info locals"];
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch),
+    );
+    let expected = r#"set pagination off
+set confirm off
+rbreak ::test1$
+rbreak ::__break$
+commands
+finish
+end
+run
+info breakpoints
+continue
+echo start_debugger_command_0\n
+info locals
+echo end_debugger_command_0\n
+continue
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_lldb_debugger_script_launch() {
+    let debugger_commands = vec!["frame variable"];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch),
+    );
+    let expected = r#"breakpoint set -n test1
+breakpoint set -n __break
+breakpoint command add -o "thread step-out"
+process launch
+breakpoint list
+continue
+script print("start_debugger_command_0")
+frame variable
+script print("end_debugger_command_0")
+continue
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_lldb_debugger_script_session() {
+    let debugger_commands = vec!["frame variable"];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Session),
+    );
+    let expected = r#"script lldb.debugger.SetAsync(False)
+breakpoint set -n test1
+breakpoint set -n __break
+breakpoint command add -o "thread step-out"
+process launch
+breakpoint list
+continue
+script print("start_debugger_command_0")
+frame variable
+script print("end_debugger_command_0")
+continue
+quit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_debugger_script_breakpoints() {
+    let debugger_commands = vec!["dv"];
+    let breakpoints = vec![
+        Breakpoint::from(BreakpointLocation::from_str("src/foo.rs:42").unwrap()),
+        Breakpoint::from(BreakpointLocation::from_str("my_crate::parse").unwrap()),
+    ];
+
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script
+        .contains("bm *!*::__break \"gu\"\nbp `src\\foo.rs:42`\nbm *!my_crate::parse\n"));
+
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script
+        .contains("commands\nfinish\nend\nbreak src/foo.rs:42\nbreak my_crate::parse\nrun\n"));
+
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        "breakpoint command add -o \"thread step-out\"\nbreakpoint set --file src/foo.rs --line 42\nbreakpoint set --name my_crate::parse\nprocess launch\n"
+    ));
+
+    let debugger_script = create_gdb_mi_debugger_script(&debugger_commands, &breakpoints);
+    assert!(debugger_script.contains(
+        "-break-insert -f __break\n-break-insert -f src/foo.rs:42\n-break-insert -f my_crate::parse\n-exec-run\n"
+    ));
+}
+
+#[test]
+fn test_debugger_script_breakpoint_options() {
+    let debugger_commands = vec!["dv"];
+    let breakpoints = vec![
+        Breakpoint {
+            location: BreakpointLocation::from_str("src/foo.rs:42").unwrap(),
+            condition: Some(String::from("s == \"a\"")),
+            hit_count: None,
+        },
+        Breakpoint {
+            location: BreakpointLocation::from_str("my_crate::parse").unwrap(),
+            condition: None,
+            hit_count: Some(1000),
+        },
+    ];
+
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        "bp `src\\foo.rs:42` \"j @@c++(s == \\\"a\\\") ''; 'gc'\"\nbm *!my_crate::parse 1000\n"
+    ));
+
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        "break src/foo.rs:42\ncondition $bpnum s == \"a\"\nbreak my_crate::parse\nignore $bpnum 999\n"
+    ));
+
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        r#"breakpoint set --file src/foo.rs --line 42 --condition "s == \"a\""
+breakpoint set --name my_crate::parse --ignore-count 999
+"#
+    ));
+
+    let debugger_script = create_gdb_mi_debugger_script(&debugger_commands, &breakpoints);
+    assert!(debugger_script.contains(
+        r#"-break-insert -f -c "s == \"a\"" src/foo.rs:42
+-break-insert -f -i 999 my_crate::parse
+"#
+    ));
+}
+
+#[test]
+fn test_debugger_script_watch() {
+    let debugger_commands = vec!["print self.head"];
+    let watch_expressions = vec!["self.head"];
+
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            watch_expressions: &watch_expressions,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    let helper = format!("python exec({})\n", python_string(JSON_CAPTURE_HELPER));
+    assert!(
+        debugger_script.starts_with(&format!("set pagination off\nset confirm off\n{}", helper))
+    );
+    assert!(debugger_script.contains(
+        "info breakpoints\ncontinue\npython debugger_test_watch(0, 'self.head')\ncommands\nsilent\npython debugger_test_watch_hit(0)\ncontinue\nend\necho start_debugger_command_0\\n\n"
+    ));
+
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            watch_expressions: &watch_expressions,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.starts_with(&format!(
+        "script exec({})\n",
+        python_string(JSON_CAPTURE_HELPER)
+    )));
+    assert!(debugger_script.contains(
+        "breakpoint list\ncontinue\nscript debugger_test_watch(0, 'self.head')\nscript print(\"start_debugger_command_0\")\n"
+    ));
+}
+
+#[test]
+fn test_debugger_script_setup() {
+    let debugger_commands = vec![];
+    let setup_commands = vec![".nvload vec.natvis"];
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions {
+            setup_commands: &setup_commands,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    let expected = r#".echo start_setup_command_0
+.nvload vec.natvis
+.echo end_setup_command_0
+bm *!*::test1
+bm *!*::__break "gu"
+g
+bl
+g
+g
+q
+"#;
+    assert_eq!(expected.to_string(), debugger_script);
+
+    let setup_commands = vec!["source printers.py"];
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            setup_commands: &setup_commands,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.starts_with(
+        "set pagination off\nset confirm off\necho start_setup_command_0\\n\nsource printers.py\necho end_setup_command_0\\n\nrbreak ::test1$\n"
+    ));
+
+    let setup_commands = vec!["command script import formatters.py"];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            setup_commands: &setup_commands,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.starts_with(
+        "script print(\"start_setup_command_0\")\ncommand script import formatters.py\nscript print(\"end_setup_command_0\")\nbreakpoint set -n test1\n"
+    ));
+}
+
+#[test]
+fn test_debugger_script_break_labels() {
+    let debugger_commands = vec![];
+    let break_labels = vec![BreakLabel {
+        label: "after_push".to_string(),
+        commands: vec!["dx v".to_string(), "dx \"a\"".to_string()],
+        expected_statements: vec![],
+    }];
+
+    let debugger_script = create_debugger_script(
+        &CdbRenderer,
+        &ScriptOptions {
+            break_labels: &break_labels,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        "bm *!*::__break \"gu\"\nbm *!*::__break_here_after_push \".frame 1; .echo start_break_label_after_push; dx v; dx \\\"a\\\"; .echo end_break_label_after_push; g\"\ng\n"
+    ));
+
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            break_labels: &break_labels,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        "break __break_here_after_push\ncommands\nup-silently\necho start_break_label_after_push\\n\ndx v\ndx \"a\"\necho end_break_label_after_push\\n\ncontinue\nend\nrun\n"
+    ));
+
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            break_labels: &break_labels,
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+    assert!(debugger_script.contains(
+        r#"breakpoint set --name __break_here_after_push --auto-continue true
+breakpoint command add -o "frame select 1" -o "script print(\"start_break_label_after_push\")" -o "dx v" -o "dx \"a\"" -o "script print(\"end_break_label_after_push\")"
+process launch
+"#
+    ));
+}
+
+#[cfg(test)]
+fn test_breakpoint_commands() -> Vec<BreakpointCommand> {
+    vec![
+        BreakpointCommand::new(
+            BreakpointTarget::Function(String::from("__break")),
+            vec![ScriptCommand::StepOut],
+        ),
+        BreakpointCommand {
+            target: BreakpointTarget::Location(BreakpointLocation::Line {
+                file: String::from("src/foo.rs"),
+                line: 42,
+            }),
+            condition: Some(String::from("s == \"a\"")),
+            hit_count: Some(3),
+            commands: vec![],
+        },
+        BreakpointCommand::new(
+            BreakpointTarget::Label(String::from("__break_here_l")),
+            vec![
+                ScriptCommand::SelectCaller,
+                ScriptCommand::Raw(String::from("p \"x\"")),
+                ScriptCommand::Continue,
+            ],
+        ),
+    ]
+}
+
+#[test]
+fn test_cdb_renderer() {
+    let renderer = CdbRenderer;
+    let breakpoints = test_breakpoint_commands()
+        .into_iter()
+        .map(|breakpoint| renderer.render(&ScriptCommand::SetBreakpoint(breakpoint)))
+        .collect::<Vec<Vec<String>>>();
+    assert_eq!(
+        vec![
+            vec![String::from("bm *!*::__break \"gu\"")],
+            vec![String::from(
+                "bp `src\\foo.rs:42` 3 \"j @@c++(s == \\\"a\\\") ''; 'gc'\""
+            )],
+            vec![String::from(
+                "bm *!*::__break_here_l \".frame 1; p \\\"x\\\"; g\""
+            )],
+        ],
+        breakpoints
+    );
+    assert!(renderer.render(&ScriptCommand::DisablePrompts).is_empty());
+    assert!(renderer.render(&ScriptCommand::WaitForStops).is_empty());
+    assert!(renderer
+        .render(&ScriptCommand::EvaluatePython(String::from("print(1)")))
+        .is_empty());
+    assert_eq!(vec!["g"], renderer.render(&ScriptCommand::Run));
+    assert_eq!(vec!["g"], renderer.render(&ScriptCommand::Continue));
+    assert_eq!(vec!["gu"], renderer.render(&ScriptCommand::StepOut));
+    assert_eq!(
+        vec![".frame 1"],
+        renderer.render(&ScriptCommand::SelectCaller)
+    );
+    assert_eq!(vec!["bl"], renderer.render(&ScriptCommand::ListBreakpoints));
+    assert_eq!(
+        vec![".echo start_debugger_command_0"],
+        renderer.render(&ScriptCommand::EchoMarker(String::from(
+            "start_debugger_command_0"
+        )))
+    );
+    assert_eq!(
+        vec!["bp /1 @$ra \"qd\""],
+        renderer.render(&ScriptCommand::DetachOnReturn)
+    );
+    assert_eq!(vec!["qd"], renderer.render(&ScriptCommand::Detach));
+    assert_eq!(vec!["q"], renderer.render(&ScriptCommand::Quit));
+    assert_eq!(
+        vec![".nvload C:\\natvis\\vec.natvis"],
+        renderer.render(&ScriptCommand::LoadVisualizer(String::from(
+            "C:\\natvis\\vec.natvis"
+        )))
+    );
+    assert_eq!(
+        vec!["dx v"],
+        renderer.render(&ScriptCommand::Raw(String::from("dx v")))
+    );
+
+    // A space separates an escaped quote at the end of the commands from the closing quote.
+    let breakpoint = BreakpointCommand::new(
+        BreakpointTarget::Function(String::from("test1")),
+        vec![ScriptCommand::Raw(String::from("bp /1 @$ra \"qd\""))],
+    );
+    assert_eq!(
+        vec!["bm *!*::test1 \"bp /1 @$ra \\\"qd\\\" \""],
+        renderer.render(&ScriptCommand::SetBreakpoint(breakpoint))
+    );
+}
+
+#[test]
+fn test_gdb_renderer() {
+    let renderer = GdbRenderer;
+    let breakpoints = test_breakpoint_commands()
+        .into_iter()
+        .map(|breakpoint| renderer.render(&ScriptCommand::SetBreakpoint(breakpoint)))
+        .collect::<Vec<Vec<String>>>();
+    assert_eq!(
+        vec![
+            vec!["rbreak ::__break$", "commands", "finish", "end"],
+            vec![
+                "break src/foo.rs:42",
+                "condition $bpnum s == \"a\"",
+                "ignore $bpnum 2"
+            ],
+            vec![
+                "break __break_here_l",
+                "commands",
+                "up-silently",
+                "p \"x\"",
+                "continue",
+                "end"
+            ],
+        ],
+        breakpoints
+    );
+    assert_eq!(
+        vec!["set pagination off", "set confirm off"],
+        renderer.render(&ScriptCommand::DisablePrompts)
+    );
+    assert!(renderer.render(&ScriptCommand::WaitForStops).is_empty());
+    assert_eq!(
+        vec!["python print(1)"],
+        renderer.render(&ScriptCommand::EvaluatePython(String::from("print(1)")))
+    );
+    assert_eq!(
+        vec![
+            "python debugger_test_watch(0, 'v.len')",
+            "commands",
+            "silent",
+            "python debugger_test_watch_hit(0)",
+            "continue",
+            "end"
+        ],
+        renderer.render(&ScriptCommand::Watch {
+            index: 0,
+            expression: String::from("v.len")
+        })
+    );
+    assert_eq!(vec!["run"], renderer.render(&ScriptCommand::Run));
+    assert_eq!(vec!["continue"], renderer.render(&ScriptCommand::Continue));
+    assert_eq!(vec!["finish"], renderer.render(&ScriptCommand::StepOut));
+    assert_eq!(
+        vec!["up-silently"],
+        renderer.render(&ScriptCommand::SelectCaller)
+    );
+    assert_eq!(
+        vec!["info breakpoints"],
+        renderer.render(&ScriptCommand::ListBreakpoints)
+    );
+    assert_eq!(
+        vec!["echo start_debugger_command_0\\n"],
+        renderer.render(&ScriptCommand::EchoMarker(String::from(
+            "start_debugger_command_0"
+        )))
+    );
+    assert_eq!(
+        vec![
+            "up-silently",
+            "tbreak *$pc",
+            "commands",
+            "detach",
+            "quit",
+            "end",
+            "down-silently"
+        ],
+        renderer.render(&ScriptCommand::DetachOnReturn)
+    );
+    assert_eq!(
+        vec!["detach", "quit"],
+        renderer.render(&ScriptCommand::Detach)
+    );
+    assert_eq!(vec!["quit"], renderer.render(&ScriptCommand::Quit));
+    assert_eq!(
+        vec!["source /src/printers.py"],
+        renderer.render(&ScriptCommand::LoadVisualizer(String::from(
+            "/src/printers.py"
+        )))
+    );
+    assert_eq!(
+        vec!["info locals"],
+        renderer.render(&ScriptCommand::Raw(String::from("info locals")))
+    );
+}
+
+#[test]
+fn test_lldb_renderer() {
+    let renderer = LldbRenderer;
+    let breakpoints = test_breakpoint_commands()
+        .into_iter()
+        .map(|breakpoint| renderer.render(&ScriptCommand::SetBreakpoint(breakpoint)))
+        .collect::<Vec<Vec<String>>>();
+    assert_eq!(
+        vec![
+            vec![
+                "breakpoint set -n __break",
+                "breakpoint command add -o \"thread step-out\""
+            ],
+            vec![
+                "breakpoint set --file src/foo.rs --line 42 --condition \"s == \\\"a\\\"\" --ignore-count 2"
+            ],
+            vec![
+                "breakpoint set --name __break_here_l --auto-continue true",
+                "breakpoint command add -o \"frame select 1\" -o \"p \\\"x\\\"\""
+            ],
+        ],
+        breakpoints
+    );
+    assert!(renderer.render(&ScriptCommand::DisablePrompts).is_empty());
+    assert_eq!(
+        vec!["script lldb.debugger.SetAsync(False)"],
+        renderer.render(&ScriptCommand::WaitForStops)
+    );
+    assert_eq!(
+        vec!["script print(1)"],
+        renderer.render(&ScriptCommand::EvaluatePython(String::from("print(1)")))
+    );
+    assert_eq!(
+        vec!["script debugger_test_watch(0, 'v.len')"],
+        renderer.render(&ScriptCommand::Watch {
+            index: 0,
+            expression: String::from("v.len")
+        })
+    );
+    assert_eq!(vec!["process launch"], renderer.render(&ScriptCommand::Run));
+    assert_eq!(vec!["continue"], renderer.render(&ScriptCommand::Continue));
+    assert_eq!(
+        vec!["thread step-out"],
+        renderer.render(&ScriptCommand::StepOut)
+    );
+    assert_eq!(
+        vec!["frame select 1"],
+        renderer.render(&ScriptCommand::SelectCaller)
+    );
+    assert_eq!(
+        vec!["breakpoint list"],
+        renderer.render(&ScriptCommand::ListBreakpoints)
+    );
+    assert_eq!(
+        vec!["script print(\"start_debugger_command_0\")"],
+        renderer.render(&ScriptCommand::EchoMarker(String::from(
+            "start_debugger_command_0"
+        )))
+    );
+    assert_eq!(
+        vec![
+            "frame select 1",
+            "breakpoint set --one-shot true --address $pc",
+            "breakpoint command add -o \"process detach\"",
+            "frame select 0"
+        ],
+        renderer.render(&ScriptCommand::DetachOnReturn)
+    );
+    assert_eq!(
+        vec!["process detach", "quit"],
+        renderer.render(&ScriptCommand::Detach)
+    );
+    assert_eq!(vec!["quit"], renderer.render(&ScriptCommand::Quit));
+    assert_eq!(
+        vec!["command script import \"/src/formatters.py\""],
+        renderer.render(&ScriptCommand::LoadVisualizer(String::from(
+            "/src/formatters.py"
+        )))
+    );
+    assert_eq!(
+        vec!["command source \"/src/formatters.lldb\""],
+        renderer.render(&ScriptCommand::LoadVisualizer(String::from(
+            "/src/formatters.lldb"
+        )))
+    );
+    assert_eq!(
+        vec!["frame variable"],
+        renderer.render(&ScriptCommand::Raw(String::from("frame variable")))
+    );
+}
+
+#[test]
+fn test_gdb_mi_renderer() {
+    let renderer = GdbMiRenderer;
+    let breakpoints = test_breakpoint_commands()
+        .into_iter()
+        .map(|breakpoint| renderer.render(&ScriptCommand::SetBreakpoint(breakpoint)))
+        .collect::<Vec<Vec<String>>>();
+    assert_eq!(
+        vec![
+            vec!["-break-insert -f __break"],
+            vec!["-break-insert -f -c \"s == \\\"a\\\"\" -i 2 src/foo.rs:42"],
+            vec!["-break-insert -f __break_here_l"],
+        ],
+        breakpoints
+    );
+    assert_eq!(
+        vec!["-gdb-set confirm off"],
+        renderer.render(&ScriptCommand::DisablePrompts)
+    );
+    assert_eq!(vec!["-exec-run"], renderer.render(&ScriptCommand::Run));
+    assert_eq!(
+        vec!["-exec-continue"],
+        renderer.render(&ScriptCommand::Continue)
+    );
+    assert_eq!(
+        vec!["-exec-finish"],
+        renderer.render(&ScriptCommand::StepOut)
+    );
+    assert_eq!(
+        vec!["-break-list"],
+        renderer.render(&ScriptCommand::ListBreakpoints)
+    );
+    assert_eq!(
+        vec!["-interpreter-exec console \"echo start_debugger_command_0\\\\n\""],
+        renderer.render(&ScriptCommand::EchoMarker(String::from(
+            "start_debugger_command_0"
+        )))
+    );
+    assert_eq!(
+        vec!["-interpreter-exec console \"up-silently\""],
+        renderer.render(&ScriptCommand::SelectCaller)
+    );
+    assert!(renderer.render(&ScriptCommand::DetachOnReturn).is_empty());
+    assert_eq!(
+        vec!["-target-detach", "-gdb-exit"],
+        renderer.render(&ScriptCommand::Detach)
+    );
+    assert_eq!(vec!["-gdb-exit"], renderer.render(&ScriptCommand::Quit));
+    assert_eq!(
+        vec!["-stack-list-locals 1"],
+        renderer.render(&ScriptCommand::Raw(String::from("-stack-list-locals 1")))
+    );
+}
+
+#[test]
+fn test_dap_renderer() {
+    let renderer = DapRenderer;
+    assert_eq!(
+        vec!["variables"],
+        renderer.render(&ScriptCommand::Raw(String::from("variables")))
+    );
+    assert!(renderer.render(&ScriptCommand::Run).is_empty());
+    assert!(renderer
+        .render(&ScriptCommand::EchoMarker(String::from(
+            "start_debugger_command_0"
+        )))
+        .is_empty());
+}
+
+#[test]
+fn test_python_string() {
+    assert_eq!(r#"'a\'b\\c\n'"#, python_string("a'b\\c\n"));
+}
+
+#[test]
+fn test_gdb_debugger_script_json_capture() {
+    let debugger_commands = vec!["info locals"];
+    let expressions = vec!["a + 1", "v.len()"];
+    let breakpoints = vec![Breakpoint::from(
+        BreakpointLocation::from_str("src/foo.rs:42").unwrap(),
+    )];
+    let debugger_script = create_debugger_script(
+        &GdbRenderer,
+        &ScriptOptions {
+            breakpoints: &breakpoints,
+            json_capture: Some(expressions.as_slice()),
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+
+    let helper = format!("python exec({})\n", python_string(JSON_CAPTURE_HELPER));
+    assert!(
+        debugger_script.starts_with(&format!("set pagination off\nset confirm off\n{}", helper))
+    );
+    assert!(debugger_script.contains(
+        "rbreak ::__break$\ncommands\npython debugger_test_capture(1, ['a + 1', 'v.len()'])\nfinish\nend\n"
+    ));
+    assert!(debugger_script.contains(
+        "break src/foo.rs:42\ncommands\npython debugger_test_capture(0, ['a + 1', 'v.len()'])\nend\n"
+    ));
+}
+
+#[test]
+fn test_lldb_debugger_script_json_capture() {
+    let debugger_commands = vec!["frame variable"];
+    let expressions = vec![r#"s == "a""#];
+    let debugger_script = create_debugger_script(
+        &LldbRenderer,
+        &ScriptOptions {
+            json_capture: Some(expressions.as_slice()),
+            ..ScriptOptions::new("test1", &debugger_commands, DebuggerMode::Launch)
+        },
+    );
+
+    let helper = format!("script exec({})\n", python_string(JSON_CAPTURE_HELPER));
+    assert!(debugger_script.starts_with(&helper));
+    assert!(debugger_script.contains(
+        r#"breakpoint command add -o "script debugger_test_capture(1, ['s == \"a\"'])" -o "thread step-out""#
+    ));
+}
+
+#[test]
+fn test_gdb_mi_debugger_script() {
+    let debugger_commands = vec![
+        "info locals",
+        "-stack-list-variables --all-values",
+        r#"print "a""#,
+    ];
+    let debugger_script = create_gdb_mi_debugger_script(&debugger_commands, &[]);
+    let expected = r#"-gdb-set confirm off
+-inferior-tty-set /dev/null
+-break-insert -f __break
+-exec-run
+-interpreter-exec console "echo start_debugger_command_0\\n"
+-interpreter-exec console "info locals"
+-interpreter-exec console "echo end_debugger_command_0\\n"
+-interpreter-exec console "echo start_debugger_command_1\\n"
+-stack-list-variables --all-values
+-interpreter-exec console "echo end_debugger_command_1\\n"
+-interpreter-exec console "echo start_debugger_command_2\\n"
+-interpreter-exec console "print \"a\""
+-interpreter-exec console "echo end_debugger_command_2\\n"
+-exec-continue
+-gdb-exit
+"#;
+
+    assert_eq!(expected.to_string(), debugger_script);
+}
+
+#[test]
+fn test_dap_debugger_script() {
+    let debugger_commands = vec!["variables", "continue", "print a"];
+    let debugger_script = create_dap_debugger_script(&debugger_commands);
+    assert_eq!("variables\ncontinue\nprint a\n", debugger_script);
+}
+
+#[test]
+fn test_debugger_mode_from_str() {
+    assert_eq!(
+        DebuggerMode::Attach,
+        DebuggerMode::from_str("attach").unwrap()
+    );
+    assert_eq!(
+        DebuggerMode::Launch,
+        DebuggerMode::from_str("Launch").unwrap()
+    );
+    assert_eq!(
+        DebuggerMode::Session,
+        DebuggerMode::from_str("session").unwrap()
+    );
+    assert_eq!(
+        "Invalid debugger mode option: `run`.",
+        format!("{}", DebuggerMode::from_str("run").unwrap_err())
+    );
+}
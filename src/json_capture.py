@@ -0,0 +1,276 @@
+# Captures the values of a frame as JSON for the `capture = "json"` option of the `debugger_test` attribute.
+#
+# The generated debugger script loads this helper into gdb or lldb and calls `debugger_test_capture` each time the
+# `__break` function is hit. It prints one line per call, `debugger_test_json: ` followed by a JSON document with the
+# function name, the locals of the frame and the values of the requested expressions.
+#
+# For the `watch` option, the script calls `debugger_test_watch` to set a watchpoint for each expression. Each time a
+# watchpoint is written, it prints `debugger_test_watch: ` followed by a JSON document with the expression, the old
+# and new values and the location of the write.
+
+import json
+
+DEBUGGER_TEST_JSON_PREFIX = "debugger_test_json: "
+DEBUGGER_TEST_WATCH_PREFIX = "debugger_test_watch: "
+
+# The depth at which nested values are captured as their text instead of their children.
+DEBUGGER_TEST_MAX_DEPTH = 4
+
+
+def _debugger_test_is_list(names):
+    return len(names) > 0 and all(name is not None and name.startswith("[") for name in names)
+
+
+def _debugger_test_gdb_value(value, depth):
+    import gdb
+
+    if not isinstance(value, gdb.Value):
+        if value is None or isinstance(value, (bool, int, float, str)):
+            return value
+        return str(value)
+
+    # Use the pretty printer of the value if there is one, i.e. the Rust pretty printers loaded by rust-gdb.
+    visualizer = gdb.default_visualizer(value)
+    if visualizer is not None:
+        children = []
+        if hasattr(visualizer, "children") and depth < DEBUGGER_TEST_MAX_DEPTH:
+            children = list(visualizer.children())
+        if children:
+            hint = visualizer.display_hint() if hasattr(visualizer, "display_hint") else None
+            values = [_debugger_test_gdb_value(child, depth + 1) for _, child in children]
+            if hint == "map":
+                return {str(key): item for key, item in zip(values[0::2], values[1::2])}
+            names = [name for name, _ in children]
+            if hint == "array" or _debugger_test_is_list(names):
+                return values
+            return dict(zip(names, values))
+
+        text = visualizer.to_string() if hasattr(visualizer, "to_string") else None
+        if isinstance(text, gdb.Value):
+            return _debugger_test_gdb_value(text, depth)
+        return None if text is None else str(text)
+
+    value_type = value.type.strip_typedefs()
+    code = value_type.code
+    if code == gdb.TYPE_CODE_BOOL:
+        return bool(value)
+    if code == gdb.TYPE_CODE_INT:
+        return int(value)
+    if code == gdb.TYPE_CODE_FLT:
+        return float(value)
+    if code == gdb.TYPE_CODE_PTR:
+        return hex(int(value))
+    if depth < DEBUGGER_TEST_MAX_DEPTH:
+        if code in (gdb.TYPE_CODE_STRUCT, gdb.TYPE_CODE_UNION):
+            fields = {}
+            for field in value_type.fields():
+                if field.name is not None and not field.artificial:
+                    fields[field.name] = _debugger_test_gdb_value(value[field], depth + 1)
+            return fields
+        if code == gdb.TYPE_CODE_ARRAY:
+            low, high = value_type.range()
+            return [_debugger_test_gdb_value(value[i], depth + 1) for i in range(low, high + 1)]
+    return str(value)
+
+
+def _debugger_test_gdb_capture(frame_index, expressions):
+    import gdb
+
+    selected = gdb.selected_frame()
+    frame = selected
+    for _ in range(frame_index):
+        frame = frame.older()
+
+    # Walk the blocks of the frame up to the block of the function, inner variables shadow outer ones.
+    variables = {}
+    block = frame.block()
+    while block is not None:
+        for symbol in block:
+            if (symbol.is_variable or symbol.is_argument) and symbol.name not in variables:
+                variables[symbol.name] = _debugger_test_gdb_value(frame.read_var(symbol, block), 0)
+        if block.function is not None:
+            break
+        block = block.superblock
+
+    values = {}
+    errors = {}
+    frame.select()
+    try:
+        for expression in expressions:
+            try:
+                values[expression] = _debugger_test_gdb_value(gdb.parse_and_eval(expression), 0)
+            except gdb.error as error:
+                errors[expression] = str(error)
+    finally:
+        selected.select()
+
+    return frame.name(), variables, values, errors
+
+
+def _debugger_test_lldb_value(value, depth):
+    import lldb
+
+    value_type = value.GetType().GetCanonicalType()
+    flags = value_type.GetTypeFlags()
+    count = value.GetNumChildren()
+    if count > 0 and depth < DEBUGGER_TEST_MAX_DEPTH and not flags & lldb.eTypeIsPointer:
+        children = [value.GetChildAtIndex(i) for i in range(count)]
+        values = [_debugger_test_lldb_value(child, depth + 1) for child in children]
+        names = [child.GetName() for child in children]
+        if _debugger_test_is_list(names):
+            return values
+        return dict(zip(names, values))
+
+    text = value.GetValue()
+    if text is None:
+        text = value.GetSummary()
+        if text is not None and len(text) >= 2 and text[0] == '"' and text[-1] == '"':
+            text = text[1:-1]
+        return text
+
+    try:
+        if value_type.GetBasicType() == lldb.eBasicTypeBool:
+            return text == "true"
+        if flags & lldb.eTypeIsInteger and not flags & lldb.eTypeIsPointer:
+            return int(text, 0)
+        if flags & lldb.eTypeIsFloat:
+            return float(text)
+    except ValueError:
+        pass
+    return text
+
+
+def _debugger_test_lldb_capture(frame_index, expressions):
+    import lldb
+
+    thread = lldb.debugger.GetSelectedTarget().GetProcess().GetSelectedThread()
+    frame = thread.GetFrameAtIndex(frame_index)
+
+    variables = {}
+    for variable in frame.GetVariables(True, True, False, True):
+        if variable.GetName() not in variables:
+            variables[variable.GetName()] = _debugger_test_lldb_value(variable, 0)
+
+    values = {}
+    errors = {}
+    for expression in expressions:
+        value = frame.EvaluateExpression(expression)
+        if value.GetError().Fail():
+            errors[expression] = value.GetError().GetCString()
+        else:
+            values[expression] = _debugger_test_lldb_value(value, 0)
+
+    return frame.GetFunctionName(), variables, values, errors
+
+
+def debugger_test_capture(frame_index, expressions):
+    """Print the locals of the given frame and the values of the expressions as a JSON document."""
+    try:
+        import gdb  # noqa: F401
+
+        capture = _debugger_test_gdb_capture
+    except ImportError:
+        capture = _debugger_test_lldb_capture
+
+    function, variables, values, errors = capture(frame_index, expressions)
+    document = {"function": function, "locals": variables, "expressions": values}
+    if errors:
+        document["errors"] = errors
+    print(DEBUGGER_TEST_JSON_PREFIX + json.dumps(document, sort_keys=True, default=str))
+
+
+# The watchpoints set by `debugger_test_watch`, by the index of the expression for gdb and the watchpoint id for lldb.
+DEBUGGER_TEST_WATCHES = {}
+
+
+def _debugger_test_print_watch(watch, new_value, function, file, line):
+    document = {
+        "expression": watch["expression"],
+        "old": watch["value"],
+        "new": new_value,
+        "function": function,
+        "file": file,
+        "line": line,
+    }
+    watch["value"] = new_value
+    print(DEBUGGER_TEST_WATCH_PREFIX + json.dumps(document, sort_keys=True, default=str))
+
+
+def _debugger_test_gdb_watch(index, expression):
+    import gdb
+
+    # Watch the location of the expression, so writes through references in other functions are recorded.
+    # The script adds the commands which call `debugger_test_watch_hit` to the watchpoint.
+    value = gdb.parse_and_eval(expression)
+    gdb.execute("watch -location " + expression, to_string=True)
+    DEBUGGER_TEST_WATCHES[index] = {
+        "expression": expression,
+        "address": value.address,
+        "value": _debugger_test_gdb_value(value, 0),
+    }
+
+
+def debugger_test_watch_hit(index):
+    """Print the write of a gdb watchpoint set by `debugger_test_watch`."""
+    import gdb
+
+    watch = DEBUGGER_TEST_WATCHES[index]
+    frame = gdb.selected_frame()
+    sal = frame.find_sal()
+    file = sal.symtab.filename if sal.symtab is not None else None
+    new_value = _debugger_test_gdb_value(watch["address"].dereference(), 0)
+    _debugger_test_print_watch(watch, new_value, frame.name(), file, sal.line)
+
+
+def _debugger_test_lldb_watch(index, expression):
+    import lldb
+
+    target = lldb.debugger.GetSelectedTarget()
+    frame = target.GetProcess().GetSelectedThread().GetSelectedFrame()
+    value = frame.GetValueForVariablePath(expression)
+    if not value.IsValid() or value.GetError().Fail():
+        value = frame.EvaluateExpression(expression)
+
+    error = lldb.SBError()
+    watchpoint = value.Watch(True, False, True, error)
+    if error.Fail():
+        raise RuntimeError("Failed to watch `%s`: %s" % (expression, error.GetCString()))
+
+    number = watchpoint.GetID()
+    DEBUGGER_TEST_WATCHES[number] = {
+        "expression": expression,
+        "address": value.GetLoadAddress(),
+        "type": value.GetType(),
+        "value": _debugger_test_lldb_value(value, 0),
+    }
+    # The watchpoint does not stop the test when the callback returns False.
+    lldb.debugger.HandleCommand("watchpoint command add -F debugger_test_watch_hit_lldb %d" % number)
+
+
+def debugger_test_watch_hit_lldb(frame, watchpoint, internal_dict):
+    """Print the write of an lldb watchpoint set by `debugger_test_watch` and continue the test."""
+    import lldb
+
+    watch = DEBUGGER_TEST_WATCHES[watchpoint.GetID()]
+    target = frame.GetThread().GetProcess().GetTarget()
+    value = target.CreateValueFromAddress(
+        watch["expression"], lldb.SBAddress(watch["address"], target), watch["type"]
+    )
+    line_entry = frame.GetLineEntry()
+    file = str(line_entry.GetFileSpec()) if line_entry.IsValid() else None
+    _debugger_test_print_watch(
+        watch, _debugger_test_lldb_value(value, 0), frame.GetFunctionName(), file, line_entry.GetLine()
+    )
+    return False
+
+
+def debugger_test_watch(index, expression):
+    """Set a watchpoint which prints the old and new values of the expression each time it is written."""
+    try:
+        import gdb  # noqa: F401
+
+        watch = _debugger_test_gdb_watch
+    except ImportError:
+        watch = _debugger_test_lldb_watch
+
+    watch(index, expression)
@@ -1,75 +1,921 @@
-mod debugger;
 mod debugger_script;
 
 use std::str::FromStr;
 
-use debugger::DebuggerType;
+use debugger_test_parser::{BreakpointLocation, DebuggerType};
 use proc_macro::TokenStream;
 use quote::{format_ident, quote, ToTokens};
-use syn::{parse::Parse, Token};
+use syn::{parse::Parse, punctuated::Punctuated, spanned::Spanned, Token};
 
-use crate::debugger_script::create_debugger_script;
+use crate::debugger_script::{
+    create_dap_debugger_script, create_debugger_script, create_gdb_mi_debugger_script, BreakLabel,
+    Breakpoint, CaptureMode, CdbRenderer, DebuggerMode, GdbRenderer, LldbRenderer, ScriptCommand,
+    ScriptOptions, ScriptRenderer,
+};
 
 struct DebuggerTest {
-    debugger: String,
+    sections: Vec<DebuggerSection>,
+}
+
+/// The commands to run and the statements to verify for a single debugger.
+struct DebuggerSection {
+    debugger_type: DebuggerType,
     commands: String,
     expected_statements: String,
+    debugger_path: Option<String>,
+    timeout: u64,
+    mode: DebuggerMode,
+    fixture: Option<String>,
+    capture: Option<CaptureMode>,
+    capture_expressions: Vec<String>,
+    watch: Vec<String>,
+    setup: Vec<String>,
+    expected_setup: Vec<String>,
+    /// The path of the visualizer file loaded by the debugger, relative to the crate's manifest directory,
+    /// i.e. the `natvis` file for cdb.
+    visualizer: Option<String>,
+    breakpoints: Vec<Breakpoint>,
+    break_labels: Vec<BreakLabel>,
+    /// The files read for `commands_file` and `expected_file`, the test is rebuilt when one of them changes.
+    tracked_files: Vec<String>,
 }
 
-impl Parse for DebuggerTest {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let debugger_meta = input.parse::<syn::MetaNameValue>()?;
-        let debugger = if debugger_meta.path.is_ident("debugger") {
-            match debugger_meta.lit {
-                syn::Lit::Str(lit_str) => lit_str.value(),
-                _ => {
-                    return Err(input.error("Expected a literal string for the value of `debugger`"))
-                }
+/// The default number of seconds to wait for the debugger to attach or detach.
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// The placeholder in a debugger script which is replaced by the crate's manifest directory when the test runs.
+const MANIFEST_DIR_PLACEHOLDER: &str = "${CARGO_MANIFEST_DIR}";
+
+/// The meta items which can be specified either at the top level of the
+/// `debugger_test` attribute or within a debugger group, i.e. `gdb(...)`.
+#[derive(Clone, Default)]
+struct SectionValues {
+    commands: Option<String>,
+    expected_statements: Option<String>,
+    commands_file: Option<String>,
+    expected_file: Option<String>,
+    debugger_path: Option<String>,
+    timeout: Option<u64>,
+    mode: Option<DebuggerMode>,
+    fixture: Option<String>,
+    capture: Option<CaptureMode>,
+    capture_expressions: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    setup: Option<String>,
+    expected_setup: Option<String>,
+    natvis: Option<String>,
+    gdb_pretty_printer: Option<String>,
+    lldb_formatter: Option<String>,
+    breakpoints: Option<Vec<BreakpointLocation>>,
+    break_labels: Vec<BreakLabelValues>,
+    breakpoint_options: Vec<BreakpointOptionValues>,
+}
+
+/// The labeled meta items for a breakpoint label, i.e. `on_break("after_push") = "dx v"`.
+#[derive(Clone)]
+struct BreakLabelValues {
+    label: String,
+    commands: Option<String>,
+    expected_statements: Option<String>,
+}
+
+/// The labeled meta items for the options of a breakpoint, i.e. `condition("src/foo.rs:42") = "i == 42"`.
+#[derive(Clone)]
+struct BreakpointOptionValues {
+    location: BreakpointLocation,
+    condition: Option<String>,
+    hit_count: Option<u64>,
+}
+
+/// Split the lines of a meta item value, removing any empty lines.
+fn value_lines(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+impl SectionValues {
+    /// Parse a meta item for a debugger section.
+    fn parse_meta(&mut self, meta: &syn::MetaNameValue) -> syn::Result<()> {
+        if meta.path.is_ident("commands") {
+            if self.commands_file.is_some() {
+                return Err(file_conflict_error(meta, "commands", "commands_file"));
+            }
+            check_duplicate(&self.commands, meta, "commands")?;
+            self.commands = Some(parse_lit_str(meta, "commands")?);
+        } else if meta.path.is_ident("expected_statements") {
+            if self.expected_file.is_some() {
+                return Err(file_conflict_error(
+                    meta,
+                    "expected_statements",
+                    "expected_file",
+                ));
+            }
+            check_duplicate(&self.expected_statements, meta, "expected_statements")?;
+            self.expected_statements = Some(parse_lit_str(meta, "expected_statements")?);
+        } else if meta.path.is_ident("commands_file") {
+            if self.commands_file.is_some() {
+                return Err(duplicate_error(meta, "commands_file"));
+            }
+            if self.commands.is_some() {
+                return Err(file_conflict_error(meta, "commands", "commands_file"));
             }
+            let (path, contents) = read_lit_file(meta, "commands_file")?;
+            self.commands_file = Some(path);
+            self.commands = Some(contents);
+        } else if meta.path.is_ident("expected_file") {
+            if self.expected_file.is_some() {
+                return Err(duplicate_error(meta, "expected_file"));
+            }
+            if self.expected_statements.is_some() {
+                return Err(file_conflict_error(
+                    meta,
+                    "expected_statements",
+                    "expected_file",
+                ));
+            }
+            let (path, contents) = read_lit_file(meta, "expected_file")?;
+            self.expected_file = Some(path);
+            self.expected_statements = Some(contents);
+        } else if meta.path.is_ident("debugger_path") {
+            check_duplicate(&self.debugger_path, meta, "debugger_path")?;
+            self.debugger_path = Some(parse_lit_str(meta, "debugger_path")?);
+        } else if meta.path.is_ident("timeout") {
+            check_duplicate(&self.timeout, meta, "timeout")?;
+            self.timeout = Some(parse_lit_int(meta, "timeout")?);
+        } else if meta.path.is_ident("mode") {
+            check_duplicate(&self.mode, meta, "mode")?;
+            let mode = parse_lit_str(meta, "mode")?;
+            self.mode = Some(
+                DebuggerMode::from_str(&mode)
+                    .map_err(|error| syn::Error::new_spanned(&meta.lit, error.to_string()))?,
+            );
+        } else if meta.path.is_ident("fixture") {
+            check_duplicate(&self.fixture, meta, "fixture")?;
+            self.fixture = Some(parse_lit_str(meta, "fixture")?);
+        } else if meta.path.is_ident("capture") {
+            check_duplicate(&self.capture, meta, "capture")?;
+            let capture = parse_lit_str(meta, "capture")?;
+            self.capture = Some(
+                CaptureMode::from_str(&capture)
+                    .map_err(|error| syn::Error::new_spanned(&meta.lit, error.to_string()))?,
+            );
+        } else if meta.path.is_ident("capture_expressions") {
+            check_duplicate(&self.capture_expressions, meta, "capture_expressions")?;
+            self.capture_expressions = Some(vec![parse_lit_str(meta, "capture_expressions")?]);
+        } else if meta.path.is_ident("watch") {
+            check_duplicate(&self.watch, meta, "watch")?;
+            self.watch = Some(vec![parse_lit_str(meta, "watch")?]);
+        } else if meta.path.is_ident("setup") {
+            check_duplicate(&self.setup, meta, "setup")?;
+            self.setup = Some(parse_lit_str(meta, "setup")?);
+        } else if meta.path.is_ident("expected_setup") {
+            check_duplicate(&self.expected_setup, meta, "expected_setup")?;
+            self.expected_setup = Some(parse_lit_str(meta, "expected_setup")?);
+        } else if meta.path.is_ident("natvis") {
+            check_duplicate(&self.natvis, meta, "natvis")?;
+            self.natvis = Some(parse_lit_str(meta, "natvis")?);
+        } else if meta.path.is_ident("gdb_pretty_printer") {
+            check_duplicate(&self.gdb_pretty_printer, meta, "gdb_pretty_printer")?;
+            self.gdb_pretty_printer = Some(parse_lit_str(meta, "gdb_pretty_printer")?);
+        } else if meta.path.is_ident("lldb_formatter") {
+            check_duplicate(&self.lldb_formatter, meta, "lldb_formatter")?;
+            self.lldb_formatter = Some(parse_lit_str(meta, "lldb_formatter")?);
+        } else if meta.path.is_ident("breakpoints") {
+            check_duplicate(&self.breakpoints, meta, "breakpoints")?;
+            self.breakpoints = Some(parse_breakpoints(&[parse_lit(meta, "breakpoints")?])?);
         } else {
-            return Err(input.error("Expected value `debugger`"));
-        };
+            return Err(syn::Error::new_spanned(
+                &meta.path,
+                "Unexpected value, expected one of `debugger`, `commands`, `expected_statements`, `commands_file`, `expected_file`, `debugger_path`, `timeout`, `mode`, `fixture`, `capture`, `capture_expressions`, `watch`, `setup`, `expected_setup`, `natvis`, `gdb_pretty_printer`, `lldb_formatter`, `breakpoints`, `on_break(\"<label>\")`, `expected_on_break(\"<label>\")`, `condition(\"<breakpoint>\")` or `hit_count(\"<breakpoint>\")`",
+            ));
+        }
 
-        input.parse::<Token![,]>()?;
+        Ok(())
+    }
 
-        let commands_meta = input.parse::<syn::MetaNameValue>()?;
-        let commands = if commands_meta.path.is_ident("commands") {
-            match commands_meta.lit {
-                syn::Lit::Str(lit_str) => lit_str.value(),
-                _ => {
-                    return Err(input.error("Expected a literal string for the value of `commands`"))
-                }
+    /// Parse a meta item with a list of values, i.e. `breakpoints = ["src/foo.rs:42", "my_crate::parse"]`.
+    fn parse_list(&mut self, list: &ListMeta) -> syn::Result<()> {
+        let values = list.values.iter().cloned().collect::<Vec<syn::LitStr>>();
+        if list.path.is_ident("breakpoints") {
+            if self.breakpoints.is_some() {
+                return Err(list.duplicate_error("breakpoints"));
             }
+            self.breakpoints = Some(parse_breakpoints(&values)?);
+        } else if list.path.is_ident("capture_expressions") {
+            if self.capture_expressions.is_some() {
+                return Err(list.duplicate_error("capture_expressions"));
+            }
+            self.capture_expressions = Some(values.iter().map(|value| value.value()).collect());
+        } else if list.path.is_ident("watch") {
+            if self.watch.is_some() {
+                return Err(list.duplicate_error("watch"));
+            }
+            self.watch = Some(values.iter().map(|value| value.value()).collect());
+        } else {
+            return Err(syn::Error::new_spanned(
+                &list.path,
+                "Unexpected list value, expected one of `debugger`, `breakpoints`, `capture_expressions` or `watch`",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parse a labeled meta item for a breakpoint label or the options of a breakpoint.
+    fn parse_labeled(&mut self, labeled: &LabeledMeta) -> syn::Result<()> {
+        let path = &labeled.meta.path;
+        if path.is_ident("on_break") || path.is_ident("expected_on_break") {
+            self.parse_break_label(labeled)
+        } else if path.is_ident("condition") || path.is_ident("hit_count") {
+            self.parse_breakpoint_option(labeled)
         } else {
-            return Err(input.error("Expected value `commands`"));
+            Err(syn::Error::new_spanned(
+                path,
+                "Unexpected value, expected one of `on_break(\"<label>\")`, `expected_on_break(\"<label>\")`, `condition(\"<breakpoint>\")` or `hit_count(\"<breakpoint>\")`",
+            ))
+        }
+    }
+
+    /// Parse a labeled meta item for a breakpoint label, i.e. `on_break("after_push") = "dx v"`.
+    fn parse_break_label(&mut self, labeled: &LabeledMeta) -> syn::Result<()> {
+        let label = labeled.label.value();
+        debugger_test_parser::break_label_function(&label)
+            .map_err(|error| syn::Error::new_spanned(&labeled.label, error.to_string()))?;
+
+        let index = match self
+            .break_labels
+            .iter()
+            .position(|break_label| break_label.label == label)
+        {
+            Some(index) => index,
+            None => {
+                self.break_labels.push(BreakLabelValues {
+                    label: label.clone(),
+                    commands: None,
+                    expected_statements: None,
+                });
+                self.break_labels.len() - 1
+            }
+        };
+        let break_label = &mut self.break_labels[index];
+
+        let meta = &labeled.meta;
+        let (name, value) = match meta.path.is_ident("on_break") {
+            true => ("on_break", &mut break_label.commands),
+            false => ("expected_on_break", &mut break_label.expected_statements),
         };
+        if value.is_some() {
+            return Err(labeled.duplicate_error(name));
+        }
 
-        input.parse::<Token![,]>()?;
+        *value = Some(parse_lit_str(meta, name)?);
+        Ok(())
+    }
+
+    /// Parse a labeled meta item for the options of a breakpoint, i.e. `hit_count("src/foo.rs:42") = 1000`.
+    fn parse_breakpoint_option(&mut self, labeled: &LabeledMeta) -> syn::Result<()> {
+        let location = BreakpointLocation::from_str(&labeled.label.value())
+            .map_err(|error| syn::Error::new_spanned(&labeled.label, error.to_string()))?;
 
-        let expected_statements_meta = input.parse::<syn::MetaNameValue>()?;
-        let expected_statements = if expected_statements_meta
-            .path
-            .is_ident("expected_statements")
+        let index = match self
+            .breakpoint_options
+            .iter()
+            .position(|options| options.location == location)
         {
-            match expected_statements_meta.lit {
-                syn::Lit::Str(lit_str) => lit_str.value(),
-                _ => {
-                    return Err(input
-                        .error("Expected a literal string for the value of `expected_statements`"))
-                }
+            Some(index) => index,
+            None => {
+                self.breakpoint_options.push(BreakpointOptionValues {
+                    location,
+                    condition: None,
+                    hit_count: None,
+                });
+                self.breakpoint_options.len() - 1
+            }
+        };
+        let options = &mut self.breakpoint_options[index];
+
+        let meta = &labeled.meta;
+        if meta.path.is_ident("condition") {
+            if options.condition.is_some() {
+                return Err(labeled.duplicate_error("condition"));
             }
+            options.condition = Some(parse_lit_str(meta, "condition")?);
         } else {
-            return Err(input.error("Expected value `expected_statements`"));
+            if options.hit_count.is_some() {
+                return Err(labeled.duplicate_error("hit_count"));
+            }
+            let hit_count = parse_lit_int(meta, "hit_count")?;
+            if hit_count == 0 {
+                return Err(syn::Error::new_spanned(
+                    &meta.lit,
+                    "`hit_count` is the number of the first hit to stop at and must be at least 1",
+                ));
+            }
+            options.hit_count = Some(hit_count);
+        }
+
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.commands.is_none()
+            && self.expected_statements.is_none()
+            && self.debugger_path.is_none()
+            && self.fixture.is_none()
+            && self.capture_expressions.is_none()
+            && self.watch.is_none()
+            && self.setup.is_none()
+            && self.expected_setup.is_none()
+            && self.natvis.is_none()
+            && self.gdb_pretty_printer.is_none()
+            && self.lldb_formatter.is_none()
+            && self.breakpoints.is_none()
+            && self.break_labels.is_empty()
+            && self.breakpoint_options.is_empty()
+    }
+
+    /// Create the section for the given debugger, verifying all required values are set.
+    fn into_section(
+        self,
+        debugger_type: DebuggerType,
+        span: proc_macro2::Span,
+    ) -> syn::Result<DebuggerSection> {
+        let missing_value =
+            |name: &str| syn::Error::new(span, format!("Expected value `{}`", name));
+
+        // The GDB/MI driver runs in the test process, so gdb has to launch the test executable.
+        let mode = match (debugger_type, self.mode) {
+            (DebuggerType::GdbMi, Some(DebuggerMode::Attach))
+            | (DebuggerType::GdbMi, Some(DebuggerMode::Session)) => {
+                return Err(syn::Error::new(
+                    span,
+                    "debugger `gdb-mi` only supports `mode = \"launch\"`",
+                ))
+            }
+            (DebuggerType::GdbMi, _) => DebuggerMode::Launch,
+            // The DAP drivers attach to a test executable launched by the test, which runs the test the same as in launch mode.
+            (DebuggerType::GdbDap, Some(_)) | (DebuggerType::LldbDap, Some(_)) => {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "debugger `{}` does not support the `mode` option",
+                        debugger_type
+                    ),
+                ))
+            }
+            (DebuggerType::GdbDap, None) | (DebuggerType::LldbDap, None) => DebuggerMode::Launch,
+            (_, mode) => mode.unwrap_or(DebuggerMode::Attach),
+        };
+
+        // The JSON capture helper, which also sets the watchpoints, is loaded with the Python support of gdb and lldb.
+        match debugger_type {
+            DebuggerType::Gdb
+            | DebuggerType::RustGdb
+            | DebuggerType::Lldb
+            | DebuggerType::RustLldb => {}
+            _ => {
+                let option = match (&self.capture, &self.watch) {
+                    (Some(_), _) => Some("capture"),
+                    (None, Some(_)) => Some("watch"),
+                    (None, None) => None,
+                };
+                if let Some(option) = option {
+                    return Err(syn::Error::new(
+                        span,
+                        format!(
+                            "debugger `{}` does not support the `{}` option",
+                            debugger_type, option
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if self.capture_expressions.is_some() && self.capture.is_none() {
+            return Err(syn::Error::new(
+                span,
+                "`capture_expressions` can only be used with `capture = \"json\"`",
+            ));
+        }
+
+        if self.expected_setup.is_some() && self.setup.is_none() {
+            return Err(syn::Error::new(
+                span,
+                "`expected_setup` can only be used with `setup`",
+            ));
+        }
+
+        // The setup commands are run by the debugger script before the test starts.
+        match debugger_type {
+            DebuggerType::GdbMi | DebuggerType::GdbDap | DebuggerType::LldbDap
+                if self.setup.is_some() =>
+            {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "debugger `{}` does not support the `setup` option",
+                        debugger_type
+                    ),
+                ))
+            }
+            _ => {}
+        }
+
+        // Each debugger only loads the visualizer written for it, and the GDB/MI and DAP drivers do not load visualizers.
+        let unsupported_option = |option: &str| {
+            syn::Error::new(
+                span,
+                format!(
+                    "debugger `{}` does not support the `{}` option",
+                    debugger_type, option
+                ),
+            )
+        };
+        let (supported_option, visualizer) = match debugger_type {
+            DebuggerType::Cdb | DebuggerType::Mock => ("natvis", self.natvis.clone()),
+            DebuggerType::Gdb | DebuggerType::RustGdb => {
+                ("gdb_pretty_printer", self.gdb_pretty_printer.clone())
+            }
+            DebuggerType::Lldb | DebuggerType::RustLldb => {
+                ("lldb_formatter", self.lldb_formatter.clone())
+            }
+            DebuggerType::GdbMi | DebuggerType::GdbDap | DebuggerType::LldbDap => ("", None),
+        };
+        for (option, value) in [
+            ("natvis", &self.natvis),
+            ("gdb_pretty_printer", &self.gdb_pretty_printer),
+            ("lldb_formatter", &self.lldb_formatter),
+        ]
+        .iter()
+        {
+            if value.is_some() && *option != supported_option {
+                return Err(unsupported_option(option));
+            }
+        }
+
+        // Captured values are only substituted when the test sends the debugger one command at a time.
+        if let (Some(commands), Some(expected_statements)) =
+            (&self.commands, &self.expected_statements)
+        {
+            let expected_statements = expected_statements.lines().collect::<Vec<&str>>();
+            let capture_names =
+                debugger_test_parser::capture_names(&expected_statements).unwrap_or_default();
+            let references = debugger_test_parser::capture_references(commands, &capture_names);
+            if !references.is_empty() && mode != DebuggerMode::Session {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "`${{{}}}` can only be used in `commands` with `mode = \"session\"`",
+                        references[0]
+                    ),
+                ));
+            }
+        }
+
+        // The commands of a breakpoint label are run by the debugger each time the label is hit.
+        match debugger_type {
+            DebuggerType::GdbMi | DebuggerType::GdbDap | DebuggerType::LldbDap
+                if !self.break_labels.is_empty() =>
+            {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "debugger `{}` does not support the `on_break` option",
+                        debugger_type
+                    ),
+                ))
+            }
+            _ => {}
+        }
+
+        // The options of a breakpoint apply to one of the locations of `breakpoints`.
+        let mut breakpoints = self
+            .breakpoints
+            .unwrap_or_default()
+            .into_iter()
+            .map(Breakpoint::from)
+            .collect::<Vec<Breakpoint>>();
+        for options in self.breakpoint_options {
+            let breakpoint = breakpoints
+                .iter_mut()
+                .find(|breakpoint| breakpoint.location == options.location)
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        span,
+                        format!(
+                            "`{}` is not one of the locations of `breakpoints`",
+                            options.location
+                        ),
+                    )
+                })?;
+            breakpoint.condition = options.condition;
+            breakpoint.hit_count = options.hit_count;
+        }
+
+        // The DAP drivers set the breakpoints without options.
+        match debugger_type {
+            DebuggerType::GdbDap | DebuggerType::LldbDap
+                if breakpoints.iter().any(|breakpoint| {
+                    breakpoint.condition.is_some() || breakpoint.hit_count.is_some()
+                }) =>
+            {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "debugger `{}` does not support the `condition` and `hit_count` options",
+                        debugger_type
+                    ),
+                ))
+            }
+            _ => {}
+        }
+
+        let mut break_labels = Vec::new();
+        for break_label in self.break_labels {
+            let commands = match break_label.commands {
+                Some(commands) => commands,
+                None => {
+                    return Err(syn::Error::new(
+                        span,
+                        format!(
+                        "`expected_on_break(\"{0}\")` can only be used with `on_break(\"{0}\")`",
+                        break_label.label
+                    ),
+                    ))
+                }
+            };
+
+            break_labels.push(BreakLabel {
+                label: break_label.label,
+                commands: value_lines(&commands),
+                expected_statements: value_lines(
+                    &break_label.expected_statements.unwrap_or_default(),
+                ),
+            });
+        }
+
+        // The commands and expected statements are optional when the test only uses breakpoint labels.
+        let (commands, expected_statements) = match break_labels.is_empty() {
+            true => (
+                self.commands.ok_or_else(|| missing_value("commands"))?,
+                self.expected_statements
+                    .ok_or_else(|| missing_value("expected_statements"))?,
+            ),
+            false => (
+                self.commands.unwrap_or_default(),
+                self.expected_statements.unwrap_or_default(),
+            ),
         };
 
-        Ok(DebuggerTest {
-            debugger,
+        if self.fixture.is_some() && debugger_type != DebuggerType::Mock {
+            return Err(syn::Error::new(
+                span,
+                "`fixture` can only be used with the `mock` debugger",
+            ));
+        }
+
+        Ok(DebuggerSection {
+            debugger_type,
             commands,
             expected_statements,
+            debugger_path: self.debugger_path,
+            timeout: self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+            mode,
+            fixture: self.fixture,
+            capture: self.capture,
+            capture_expressions: self.capture_expressions.unwrap_or_default(),
+            watch: self.watch.unwrap_or_default(),
+            setup: value_lines(&self.setup.unwrap_or_default()),
+            expected_setup: value_lines(&self.expected_setup.unwrap_or_default()),
+            visualizer,
+            breakpoints,
+            break_labels,
+            tracked_files: self
+                .commands_file
+                .into_iter()
+                .chain(self.expected_file)
+                .collect(),
         })
     }
 }
 
+/// Parse the name of a debugger, i.e. `cdb` or `rust-gdb`.
+fn parse_debugger_type<T: ToTokens>(debugger: &str, tokens: T) -> syn::Result<DebuggerType> {
+    DebuggerType::from_str(debugger).map_err(|_| {
+        syn::Error::new_spanned(
+            tokens,
+            format!("debugger `{}` must be a valid debugger option.", debugger),
+        )
+    })
+}
+
+/// Parse the debuggers of the `debugger` meta item, each value is a comma separated list of debuggers, i.e. `gdb, lldb, cdb`.
+fn parse_debuggers(values: &[syn::LitStr]) -> syn::Result<Vec<DebuggerType>> {
+    let mut debuggers = Vec::new();
+    for value in values {
+        for debugger in value.value().split(',') {
+            debuggers.push(parse_debugger_type(debugger.trim(), value)?);
+        }
+    }
+
+    Ok(debuggers)
+}
+
+/// Parse the breakpoint locations of the `breakpoints` meta item, i.e. `src/foo.rs:42` and `my_crate::parse`.
+fn parse_breakpoints(values: &[syn::LitStr]) -> syn::Result<Vec<BreakpointLocation>> {
+    let mut breakpoints = Vec::new();
+    for value in values {
+        breakpoints.push(
+            BreakpointLocation::from_str(&value.value())
+                .map_err(|error| syn::Error::new_spanned(value, error.to_string()))?,
+        );
+    }
+
+    Ok(breakpoints)
+}
+
+/// A labeled meta item for a breakpoint label, i.e. `on_break("after_push") = "dx v"`.
+struct LabeledMeta {
+    label: syn::LitStr,
+    meta: syn::MetaNameValue,
+}
+
+impl LabeledMeta {
+    /// Get the error for a labeled meta item which is specified more than once for the same label.
+    fn duplicate_error(&self, name: &str) -> syn::Error {
+        syn::Error::new_spanned(
+            &self.label,
+            format!(
+                "`{}(\"{}\")` is specified more than once.",
+                name,
+                self.label.value()
+            ),
+        )
+    }
+}
+
+/// A meta item with a list of literal strings, i.e. `breakpoints = ["src/foo.rs:42", "my_crate::parse"]`.
+struct ListMeta {
+    path: syn::Path,
+    values: Punctuated<syn::LitStr, Token![,]>,
+}
+
+impl ListMeta {
+    /// Get the error for a list meta item which is specified more than once, i.e. `breakpoints`.
+    fn duplicate_error(&self, name: &str) -> syn::Error {
+        syn::Error::new_spanned(
+            &self.path,
+            format!("`{}` is specified more than once.", name),
+        )
+    }
+}
+
+/// A meta item of the `debugger_test` attribute.
+/// The same as `syn::Meta`, with the addition of labeled meta items which `syn::Meta` does not parse.
+enum AttributeMeta {
+    NameValue(syn::MetaNameValue),
+    NameList(ListMeta),
+    Labeled(LabeledMeta),
+    List(syn::Path, Punctuated<AttributeMeta, Token![,]>),
+    Path(syn::Path),
+}
+
+impl Parse for AttributeMeta {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path = input.call(syn::Path::parse_mod_style)?;
+        if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+
+            // A labeled meta item has a single literal string within the parentheses and a value.
+            if input.peek(Token![=]) {
+                let label = content.parse::<syn::LitStr>()?;
+                if !content.is_empty() {
+                    return Err(
+                        content.error("Expected a single label, i.e. `on_break(\"label\")`")
+                    );
+                }
+
+                let meta = syn::MetaNameValue {
+                    path,
+                    eq_token: input.parse()?,
+                    lit: input.parse()?,
+                };
+                return Ok(AttributeMeta::Labeled(LabeledMeta { label, meta }));
+            }
+
+            let nested = content.parse_terminated(AttributeMeta::parse)?;
+            return Ok(AttributeMeta::List(path, nested));
+        }
+
+        if input.peek(Token![=]) && input.peek2(syn::token::Bracket) {
+            input.parse::<Token![=]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let values = content.parse_terminated(<syn::LitStr as Parse>::parse)?;
+            return Ok(AttributeMeta::NameList(ListMeta { path, values }));
+        }
+
+        if input.peek(Token![=]) {
+            return Ok(AttributeMeta::NameValue(syn::MetaNameValue {
+                path,
+                eq_token: input.parse()?,
+                lit: input.parse()?,
+            }));
+        }
+
+        Ok(AttributeMeta::Path(path))
+    }
+}
+
+/// Parse a debugger group, i.e. `gdb(commands = "...", expected_statements = "...")`.
+/// Debuggers which are not valid identifiers use an underscore, i.e. `rust_gdb(...)`.
+fn parse_debugger_group(
+    path: &syn::Path,
+    nested: &Punctuated<AttributeMeta, Token![,]>,
+) -> syn::Result<DebuggerSection> {
+    let debugger = path
+        .get_ident()
+        .map(|ident| ident.to_string().replace('_', "-"))
+        .ok_or_else(|| syn::Error::new_spanned(path, "Expected the name of a debugger"))?;
+    let debugger_type = parse_debugger_type(&debugger, path)?;
+
+    let mut values = SectionValues::default();
+    for meta in nested.iter() {
+        match meta {
+            AttributeMeta::NameValue(meta) => values.parse_meta(meta)?,
+            AttributeMeta::NameList(list) => values.parse_list(list)?,
+            AttributeMeta::Labeled(labeled) => values.parse_labeled(labeled)?,
+            AttributeMeta::List(path, _) | AttributeMeta::Path(path) => {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "Expected a meta item of the form `name = \"value\"`",
+                ))
+            }
+        }
+    }
+
+    values.into_section(debugger_type, path.span())
+}
+
+/// Get the literal of a meta item which expects a literal string.
+fn parse_lit(meta: &syn::MetaNameValue, name: &str) -> syn::Result<syn::LitStr> {
+    match &meta.lit {
+        syn::Lit::Str(lit_str) => Ok(lit_str.clone()),
+        lit => Err(syn::Error::new_spanned(
+            lit,
+            format!("Expected a literal string for the value of `{}`", name),
+        )),
+    }
+}
+
+/// Get the value of a meta item which expects a literal string.
+fn parse_lit_str(meta: &syn::MetaNameValue, name: &str) -> syn::Result<String> {
+    match &meta.lit {
+        syn::Lit::Str(lit_str) => Ok(lit_str.value()),
+        lit => Err(syn::Error::new_spanned(
+            lit,
+            format!("Expected a literal string for the value of `{}`", name),
+        )),
+    }
+}
+
+/// Resolve a path relative to the manifest directory of the crate being compiled, i.e. `tests/vec.natvis`.
+fn manifest_path(file: &str) -> String {
+    let path = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) => std::path::PathBuf::from(manifest_dir).join(file),
+        None => std::path::PathBuf::from(file),
+    };
+    path.to_string_lossy().into_owned()
+}
+
+/// Read the file of a meta item which expects a path relative to the crate's manifest directory, i.e. `tests/dbg/vec.cmds`.
+/// Returns the resolved path of the file and its contents.
+fn read_lit_file(meta: &syn::MetaNameValue, name: &str) -> syn::Result<(String, String)> {
+    let path = manifest_path(&parse_lit_str(meta, name)?);
+    let contents = std::fs::read_to_string(&path).map_err(|error| {
+        syn::Error::new_spanned(
+            &meta.lit,
+            format!("Failed to read `{}` for `{}`: {}", path, name, error),
+        )
+    })?;
+    Ok((path, contents))
+}
+
+/// Get the error for a meta item which is specified more than once, i.e. `commands_file`.
+fn duplicate_error(meta: &syn::MetaNameValue, name: &str) -> syn::Error {
+    syn::Error::new_spanned(
+        &meta.path,
+        format!("`{}` is specified more than once.", name),
+    )
+}
+
+/// Verify a meta item which can only be specified once has not already been set.
+fn check_duplicate<T>(value: &Option<T>, meta: &syn::MetaNameValue, name: &str) -> syn::Result<()> {
+    match value {
+        Some(_) => Err(duplicate_error(meta, name)),
+        None => Ok(()),
+    }
+}
+
+/// Get the error for a value which is specified both inline and as a file, i.e. `commands` and `commands_file`.
+fn file_conflict_error(meta: &syn::MetaNameValue, name: &str, file_name: &str) -> syn::Error {
+    syn::Error::new_spanned(
+        &meta.path,
+        format!("`{}` and `{}` cannot both be specified", name, file_name),
+    )
+}
+
+/// Get the value of a meta item which expects a literal integer.
+fn parse_lit_int(meta: &syn::MetaNameValue, name: &str) -> syn::Result<u64> {
+    match &meta.lit {
+        syn::Lit::Int(lit_int) => lit_int.base10_parse::<u64>(),
+        lit => Err(syn::Error::new_spanned(
+            lit,
+            format!("Expected a literal integer for the value of `{}`", name),
+        )),
+    }
+}
+
+impl Parse for DebuggerTest {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut debuggers = Vec::new();
+        let mut values = SectionValues::default();
+        let mut sections = Vec::new();
+
+        let metas = Punctuated::<AttributeMeta, Token![,]>::parse_terminated(input)?;
+        for meta in metas.iter() {
+            match meta {
+                // The debugger meta item can be a list or repeated to add more debuggers.
+                AttributeMeta::NameValue(meta) if meta.path.is_ident("debugger") => {
+                    debuggers.extend(parse_debuggers(&[parse_lit(meta, "debugger")?])?)
+                }
+                AttributeMeta::NameList(list) if list.path.is_ident("debugger") => {
+                    let values = list.values.iter().cloned().collect::<Vec<syn::LitStr>>();
+                    debuggers.extend(parse_debuggers(&values)?)
+                }
+                AttributeMeta::NameValue(meta) => values.parse_meta(meta)?,
+                AttributeMeta::NameList(list) => values.parse_list(list)?,
+                AttributeMeta::Labeled(labeled) => values.parse_labeled(labeled)?,
+                AttributeMeta::List(path, nested) => {
+                    sections.push(parse_debugger_group(path, nested)?)
+                }
+                AttributeMeta::Path(path) => {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "Expected a meta item of the form `name = \"value\"` or a debugger group",
+                    ))
+                }
+            }
+        }
+
+        let missing_value =
+            |name: &str| syn::Error::new(input.span(), format!("Expected value `{}`", name));
+
+        // The top level meta items apply to each debugger in the `debugger` meta item.
+        if debuggers.is_empty() {
+            if sections.is_empty() || !values.is_empty() {
+                return Err(missing_value("debugger"));
+            }
+        } else {
+            if values.debugger_path.is_some() && debuggers.len() > 1 {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "`debugger_path` can only be used with a single debugger",
+                ));
+            }
+
+            let mut top_level_sections = Vec::new();
+            for debugger_type in debuggers {
+                top_level_sections.push(values.clone().into_section(debugger_type, input.span())?);
+            }
+            top_level_sections.append(&mut sections);
+            sections = top_level_sections;
+        }
+
+        for (i, section) in sections.iter().enumerate() {
+            if sections[..i]
+                .iter()
+                .any(|other| other.debugger_type == section.debugger_type)
+            {
+                return Err(syn::Error::new(
+                    input.span(),
+                    format!(
+                        "debugger `{}` is specified more than once.",
+                        section.debugger_type
+                    ),
+                ));
+            }
+        }
+
+        Ok(DebuggerTest { sections })
+    }
+}
+
 #[proc_macro_attribute]
 pub fn debugger_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let invoc = match syn::parse::<DebuggerTest>(attr) {
@@ -87,122 +933,505 @@ pub fn debugger_test(attr: TokenStream, item: TokenStream) -> TokenStream {
         _ => panic!("must be attached to a function"),
     };
 
-    let debugger_commands = &invoc
+    // Generate a test function for each of the specified debuggers.
+    let mut debugger_test_fns = proc_macro2::TokenStream::new();
+    for section in invoc.sections.iter() {
+        debugger_test_fns.extend(generate_debugger_test(section, func));
+    }
+
+    debugger_test_fns.extend(item.to_token_stream());
+    debugger_test_fns.into()
+}
+
+/// Stop the debugger at a breakpoint with a label, i.e. `break_here!("after_push")`.
+/// Each time the breakpoint is hit, the debugger runs the commands of `on_break("after_push")` and continues.
+#[proc_macro]
+pub fn break_here(input: TokenStream) -> TokenStream {
+    let label = match syn::parse::<syn::LitStr>(input) {
+        Ok(s) => s,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let function_ident = match debugger_test_parser::break_label_function(&label.value()) {
+        Ok(function) => format_ident!("{}", function),
+        Err(error) => {
+            return syn::Error::new_spanned(&label, error.to_string())
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    // The debugger sets a breakpoint at the function of the label.
+    quote!({
+        #[inline(never)]
+        #[allow(non_snake_case)]
+        fn #function_ident() {
+            // Read the label so the functions of different labels are not merged.
+            static LABEL: &str = #label;
+            let _ = unsafe { std::ptr::read_volatile(&LABEL) };
+        }
+        #function_ident();
+    })
+    .into()
+}
+
+/// Generate the test function that launches the section's debugger for the `debugger_test` function.
+fn generate_debugger_test(
+    section: &DebuggerSection,
+    func: &syn::ItemFn,
+) -> proc_macro2::TokenStream {
+    let debugger_type = &section.debugger_type;
+    let mode = section.mode;
+    let breakpoints = &section.breakpoints;
+    let break_labels = &section.break_labels;
+    let debugger_commands = &section
         .commands
         .trim()
         .lines()
-        .into_iter()
         .map(|line| line.trim())
         .collect::<Vec<&str>>();
 
-    let debugger_type = DebuggerType::from_str(invoc.debugger.as_str()).expect(
-        format!(
-            "debugger `{}` must be a valid debugger option.",
-            invoc.debugger.as_str()
-        )
-        .as_str(),
-    );
-    let debugger_executable_path = debugger::get_debugger(&debugger_type);
-
     let fn_name = func.sig.ident.to_string();
     let fn_ident = format_ident!("{}", fn_name);
-    let test_fn_name = format!("{}__{}", fn_name, debugger_type.to_string());
+    let test_fn_name = format!(
+        "{}__{}",
+        fn_name,
+        debugger_type.to_string().replace('-', "_")
+    );
     let test_fn_ident = format_ident!("{}", test_fn_name);
 
-    let debugger_script_contents = create_debugger_script(&fn_name, debugger_commands);
-
     // Trim all whitespace and remove any empty lines.
-    let expected_statements = &invoc
+    let expected_statements = &section
         .expected_statements
         .trim()
         .lines()
         .collect::<Vec<&str>>();
 
-    // Create the cli for the given debugger.
-    let (debugger_command_line, cfg_attr) = match debugger_type {
+    let debugger_name = debugger_type.to_string();
+    let timeout = section.timeout;
+
+    // The expressions evaluated by the JSON capture helper at each breakpoint, in addition to the locals.
+    let capture_expressions = section
+        .capture_expressions
+        .iter()
+        .map(|expression| expression.trim())
+        .collect::<Vec<&str>>();
+    let json_capture = section
+        .capture
+        .map(|CaptureMode::Json| capture_expressions.as_slice());
+
+    // The expressions watched from the first stop of the test.
+    let watch_expressions = section
+        .watch
+        .iter()
+        .map(|expression| expression.trim())
+        .collect::<Vec<&str>>();
+
+    let setup_commands = section
+        .setup
+        .iter()
+        .map(|line| line.as_str())
+        .collect::<Vec<&str>>();
+    // The visualizer path is resolved when the test runs, so the script refers to the manifest directory by a placeholder.
+    let visualizer = section
+        .visualizer
+        .as_ref()
+        .map(|file| format!("{}/{}", MANIFEST_DIR_PLACEHOLDER, file));
+    let script_options = ScriptOptions {
+        visualizer: visualizer.as_deref(),
+        setup_commands: &setup_commands,
+        breakpoints,
+        json_capture,
+        break_labels,
+        watch_expressions: &watch_expressions,
+        ..ScriptOptions::new(&fn_name, debugger_commands, mode)
+    };
+
+    // An explicit debugger path overrides the default lookup for the given debugger.
+    let debugger_executable_path = match &section.debugger_path {
+        Some(debugger_path) => quote!(debugger_test_parser::expand_debugger_path(#debugger_path)?),
+        None => quote!(debugger_test_parser::get_debugger(&#debugger_name.parse()?)),
+    };
+
+    // Create the debugger script and the arguments for the given debugger.
+    let (debugger_script_contents, debugger_args, cfg_attr, launch_help) = match debugger_type {
         DebuggerType::Cdb => {
-            let debugger_path = debugger_executable_path.to_string_lossy().to_string();
-            let command_line = quote!(
-                match std::process::Command::new(#debugger_path)
-                    .stdout(std::process::Stdio::from(debugger_stdout_file))
-                    .stderr(std::process::Stdio::from(debugger_stderr_file))
+            let debugger_script_contents = create_debugger_script(&CdbRenderer, &script_options);
+            let debugger_args = match mode {
+                DebuggerMode::Attach => quote!(
                     .arg("-pd")
                     .arg("-p")
-                    .arg(pid.to_string())
+                    .arg(std::process::id().to_string())
                     .arg("-cf")
                     .arg(&debugger_script_path)
-                    .spawn() {
-                        Ok(child) => child,
-                        Err(error) => {
-                            return Err(std::boxed::Box::from(format!("Failed to launch CDB: {}\n", error.to_string())));
-                        }
-                }
-            );
+                ),
+                DebuggerMode::Launch => quote!(
+                    .arg("-cf")
+                    .arg(&debugger_script_path)
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+                DebuggerMode::Session => quote!(
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+            };
 
             // cdb is only supported on Windows.
             let cfg_attr = quote!(
                 #[cfg_attr(not(target_os = "windows"), ignore = "test only runs on windows platforms.")]
             );
 
-            (command_line, cfg_attr)
+            (debugger_script_contents, debugger_args, cfg_attr, "")
         }
-    };
-
-    // Create the test function that will launch the debugger and run debugger commands.
-    let mut debugger_test_fn = proc_macro::TokenStream::from(quote!(
-        #[test]
-        #cfg_attr
-        fn #test_fn_ident() -> std::result::Result<(), Box<dyn std::error::Error>> {
-            use std::io::Read;
-            use std::io::Write;
+        DebuggerType::Gdb | DebuggerType::RustGdb => {
+            let debugger_script_contents = create_debugger_script(&GdbRenderer, &script_options);
+            let debugger_args = match mode {
+                DebuggerMode::Attach => quote!(
+                    .arg("-batch")
+                    .arg("-p")
+                    .arg(std::process::id().to_string())
+                    .arg("-x")
+                    .arg(&debugger_script_path)
+                ),
+                DebuggerMode::Launch => quote!(
+                    .arg("-batch")
+                    .arg("-x")
+                    .arg(&debugger_script_path)
+                    .arg("--args")
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+                DebuggerMode::Session => quote!(
+                    .arg("-q")
+                    .arg("--args")
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+            };
 
-            let pid = std::process::id();
-            let current_exe_filename = std::env::current_exe()?.file_stem().expect("must have a valid file name").to_string_lossy().to_string();
+            // gdb tests are only run on Linux.
+            let cfg_attr = quote!(
+                #[cfg_attr(not(target_os = "linux"), ignore = "test only runs on linux platforms.")]
+            );
 
-            // Create a temporary file to store the debugger script to run.
-            let debugger_script_filename = format!("{}_{}.debugger_script", current_exe_filename, #test_fn_name);
-            let debugger_script_path = std::env::temp_dir().join(debugger_script_filename);
+            // The rust-gdb wrapper is shipped in the `bin` directory of rustup toolchains.
+            let launch_help = match debugger_type {
+                DebuggerType::RustGdb => "rust-gdb was not found in the toolchain sysroot or on the PATH, set `RUST_GDB_DEBUGGER_DIR` to the directory containing it.\n",
+                _ => "",
+            };
 
-            // Write the contents of the debugger script to a new file.
-            let mut debugger_script = std::fs::File::create(&debugger_script_path)?;
-            writeln!(debugger_script, #debugger_script_contents)?;
+            (
+                debugger_script_contents,
+                debugger_args,
+                cfg_attr,
+                launch_help,
+            )
+        }
+        DebuggerType::Lldb | DebuggerType::RustLldb => {
+            let debugger_script_contents = create_debugger_script(&LldbRenderer, &script_options);
+            let debugger_args = match mode {
+                DebuggerMode::Attach => quote!(
+                    .arg("--batch")
+                    .arg("-p")
+                    .arg(std::process::id().to_string())
+                    .arg("-s")
+                    .arg(&debugger_script_path)
+                ),
+                DebuggerMode::Launch => quote!(
+                    .arg("--batch")
+                    .arg("-s")
+                    .arg(&debugger_script_path)
+                    .arg("--")
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+                DebuggerMode::Session => quote!(
+                    .arg("--no-use-colors")
+                    .arg("--")
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+            };
 
-            // Create a temporary file to store the stdout and stderr from the debugger output.
-            let debugger_stdout_path = debugger_script_path.with_extension("debugger_out");
-            let debugger_stderr_path = debugger_script_path.with_extension("debugger_err");
+            // lldb tests are only run on Linux and macOS.
+            let cfg_attr = quote!(
+                #[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore = "test only runs on linux and macos platforms.")]
+            );
 
-            let debugger_stdout_file = std::fs::File::create(&debugger_stdout_path)?;
-            let debugger_stderr_file = std::fs::File::create(&debugger_stderr_path)?;
+            // The rust-lldb wrapper is shipped in the `bin` directory of rustup toolchains.
+            let launch_help = match debugger_type {
+                DebuggerType::RustLldb => "rust-lldb was not found in the toolchain sysroot or on the PATH, set `RUST_LLDB_DEBUGGER_DIR` to the directory containing it.\n",
+                _ => "",
+            };
 
-            // Start the debugger and run the debugger commands.
-            let mut child = #debugger_command_line;
+            (
+                debugger_script_contents,
+                debugger_args,
+                cfg_attr,
+                launch_help,
+            )
+        }
+        DebuggerType::GdbMi => {
+            let debugger_script_contents =
+                create_gdb_mi_debugger_script(debugger_commands, breakpoints);
+            let debugger_args = quote!(
+                .arg("--interpreter=mi3")
+                .arg("--quiet")
+                .arg("--args")
+                .arg(std::env::current_exe()?)
+                .args(&test_args)
+            );
+
+            // gdb tests are only run on Linux.
+            let cfg_attr = quote!(
+                #[cfg_attr(not(target_os = "linux"), ignore = "test only runs on linux platforms.")]
+            );
 
-            // Wait for the debugger to launch
-            // On Windows, use the IsDebuggerPresent API to check if a debugger is present
-            // for the current process. https://docs.microsoft.com/en-us/windows/win32/api/debugapi/nf-debugapi-isdebuggerpresent
-            #[cfg(windows)]
-            extern "stdcall" {
-                fn IsDebuggerPresent() -> i32;
+            (debugger_script_contents, debugger_args, cfg_attr, "")
+        }
+        DebuggerType::GdbDap | DebuggerType::LldbDap => {
+            let debugger_script_contents = create_dap_debugger_script(debugger_commands);
+            let (debugger_args, cfg_attr) = match debugger_type {
+                DebuggerType::GdbDap => (
+                    quote!(.arg("--interpreter=dap")),
+                    quote!(
+                        #[cfg_attr(not(target_os = "linux"), ignore = "test only runs on linux platforms.")]
+                    ),
+                ),
+                _ => (
+                    quote!(),
+                    quote!(
+                        #[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore = "test only runs on linux and macos platforms.")]
+                    ),
+                ),
             };
-            #[cfg(windows)]
-            unsafe {
-                while IsDebuggerPresent() == 0 {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
+
+            (debugger_script_contents, debugger_args, cfg_attr, "")
+        }
+        DebuggerType::Mock => {
+            // The mock debugger runs the same script as cdb and prints the output from the fixture file.
+            let debugger_script_contents = create_debugger_script(&CdbRenderer, &script_options);
+            let fixture_args = match &section.fixture {
+                Some(fixture) => quote!(
+                    .arg("--fixture")
+                    .arg(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#fixture))
+                ),
+                None => quote!(),
+            };
+            let debugger_args = match mode {
+                DebuggerMode::Attach => quote!(
+                    #fixture_args
+                    .arg("-p")
+                    .arg(std::process::id().to_string())
+                    .arg("-cf")
+                    .arg(&debugger_script_path)
+                ),
+                DebuggerMode::Launch => quote!(
+                    #fixture_args
+                    .arg("-cf")
+                    .arg(&debugger_script_path)
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+                DebuggerMode::Session => quote!(
+                    #fixture_args
+                    .arg(std::env::current_exe()?)
+                    .args(&test_args)
+                ),
+            };
+
+            // The mock debugger runs on all platforms.
+            let cfg_attr = quote!();
+
+            let launch_help = "The mock debugger is the `debugger_test_mock` binary of the debugger_test package, set `MOCK_DEBUGGER_DIR` to the directory containing it.\n";
+
+            (
+                debugger_script_contents,
+                debugger_args,
+                cfg_attr,
+                launch_help,
+            )
+        }
+    };
+
+    // The debugger passes its environment on to the launched test executable.
+    let debugger_env = match mode {
+        DebuggerMode::Attach => quote!(),
+        DebuggerMode::Launch | DebuggerMode::Session => {
+            quote!(.env(LAUNCH_STATUS_ENV_VAR, &launch_status_path))
+        }
+    };
+
+    // Start the debugger and run the debugger commands.
+    let debugger_command_line = quote!(
+        match std::process::Command::new(&debugger_executable_path)
+            .stdout(std::process::Stdio::from(debugger_stdout_file))
+            .stderr(std::process::Stdio::from(debugger_stderr_file))
+            #debugger_args
+            #debugger_env
+            .spawn() {
+                Ok(child) => child,
+                Err(error) => {
+                    return Err(std::boxed::Box::from(format!("Failed to launch {} `{}`: {}\n{}", #debugger_name, debugger_executable_path.display(), error.to_string(), #launch_help)));
                 }
-            }
+        }
+    );
+
+    // Run only this test in the launched test executable.
+    let launch_setup = quote!(
+        // The name of the test is its module path without the crate name.
+        let module_path = module_path!();
+        let test_name = match module_path.find("::") {
+            Some(index) => format!("{}::{}", &module_path[index + 2..], #test_fn_name),
+            None => #test_fn_name.to_string(),
+        };
+        let test_args = ["--exact", test_name.as_str(), "--nocapture"];
 
-            // Wait 3 seconds to ensure the debugger is in control of the process.
-            std::thread::sleep(std::time::Duration::from_secs(3));
+        // The launched test executable writes to the status file once the test function has completed.
+        let launch_status_path = debugger_script_path.with_extension("debugger_status");
+        if launch_status_path.exists() {
+            std::fs::remove_file(&launch_status_path)?;
+        }
+    );
+
+    let run_debugger = match mode {
+        // The mock debugger does not attach to the test process.
+        DebuggerMode::Attach if *debugger_type == DebuggerType::Mock => quote!(
+            let mut child = #debugger_command_line;
+
+            // Call the test function.
+            #fn_ident();
+        ),
+        DebuggerMode::Attach => quote!(
+            let mut child = #debugger_command_line;
+
+            // Declare the debugger as the ptracer of the test process and wait for it to attach.
+            let attached = debugger_attach_guard
+                .declare_debugger(&child)
+                .and_then(|_| debugger_test_parser::wait_for_debugger_attach(&mut child, timeout));
+            if let Err(error) = attached {
+                let _ = child.kill();
+                let _ = child.wait();
+
+                let mut debugger_stdout = String::new();
+                let mut debugger_stdout_file = std::fs::File::open(&debugger_stdout_path)?;
+                debugger_stdout_file.read_to_string(&mut debugger_stdout)?;
+
+                let mut debugger_stderr = String::new();
+                let mut debugger_stderr_file = std::fs::File::open(&debugger_stderr_path)?;
+                debugger_stderr_file.read_to_string(&mut debugger_stderr)?;
+                return Err(std::boxed::Box::from(format!("{}\n{}\n{}\n", error, debugger_stderr, debugger_stdout)));
+            }
 
             // Call the test function.
             #fn_ident();
+        ),
+        DebuggerMode::Launch | DebuggerMode::Session => quote!(
+            #launch_setup
+
+            let mut child = #debugger_command_line;
+        ),
+    };
 
-            // Wait for the debugger to exit.
-            std::thread::sleep(std::time::Duration::from_secs(3));
+    // Only one debugger can attach to the test process at a time, so a test which attaches a debugger waits for the
+    // other ones. The guard is held until the end of the test, after the debugger has detached.
+    let debugger_attach_guard = match mode {
+        DebuggerMode::Attach if *debugger_type != DebuggerType::Mock => quote!(
+            let debugger_attach_guard = debugger_test_parser::allow_debugger_attach()?;
+        ),
+        _ => quote!(),
+    };
+
+    let collect_debugger_output = match debugger_type {
+        // The GDB/MI driver sends the commands of the debugger script to gdb and collects the results.
+        DebuggerType::GdbMi => quote!(
+            #launch_setup
+
+            let mut debugger_command = std::process::Command::new(&debugger_executable_path);
+            debugger_command
+                .stderr(std::process::Stdio::from(debugger_stderr_file))
+                #debugger_args
+                #debugger_env;
+            let debugger_stdout = match debugger_test_parser::run_gdb_mi(debugger_command, debugger_script_contents, timeout) {
+                Ok(debugger_stdout) => debugger_stdout,
+                Err(error) => {
+                    let mut debugger_stderr = String::new();
+                    let mut debugger_stderr_file = std::fs::File::open(&debugger_stderr_path)?;
+                    debugger_stderr_file.read_to_string(&mut debugger_stderr)?;
+                    return Err(std::boxed::Box::from(format!("Failed to run {} `{}`: {}\n{}\n", #debugger_name, debugger_executable_path.display(), error, debugger_stderr)));
+                }
+            };
+            println!("Debugger stdout:\n{}\n", &debugger_stdout);
+        ),
+        // The DAP driver attaches the debug adapter to a test executable launched by the test and collects the results.
+        DebuggerType::GdbDap | DebuggerType::LldbDap => {
+            // Debug adapters expect the absolute path of source files.
+            let dap_breakpoints = breakpoints.iter().map(|breakpoint| match &breakpoint.location {
+                BreakpointLocation::Line { file, line } => quote!(
+                    format!("{}:{}", std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#file).display(), #line)
+                ),
+                BreakpointLocation::Function(function) => quote!(#function.to_string()),
+            });
 
+            quote!(
+            #launch_setup
+
+            let mut debugger_command = std::process::Command::new(&debugger_executable_path);
+            debugger_command
+                .stderr(std::process::Stdio::from(debugger_stderr_file))
+                #debugger_args;
+            let test_executable_path = std::env::current_exe()?;
+            let mut test_command = std::process::Command::new(&test_executable_path);
+            test_command
+                .args(&test_args)
+                .stdout(std::process::Stdio::null())
+                #debugger_env;
+            let breakpoints: Vec<String> = vec![#(#dap_breakpoints),*];
+            let debugger_stdout = match debugger_test_parser::run_dap(#debugger_name, debugger_command, test_command, &test_executable_path, &breakpoints, debugger_script_contents, timeout) {
+                Ok(debugger_stdout) => debugger_stdout,
+                Err(error) => {
+                    let mut debugger_stderr = String::new();
+                    let mut debugger_stderr_file = std::fs::File::open(&debugger_stderr_path)?;
+                    debugger_stderr_file.read_to_string(&mut debugger_stderr)?;
+                    return Err(std::boxed::Box::from(format!("Failed to run {} `{}`: {}\n{}\n", #debugger_name, debugger_executable_path.display(), error, debugger_stderr)));
+                }
+            };
+            println!("Debugger stdout:\n{}\n", &debugger_stdout);
+            )
+        }
+        // The session driver sends the debugger script to the debugger one step at a time and collects the output.
+        _ if mode == DebuggerMode::Session => quote!(
+            #launch_setup
+
+            let mut debugger_command = std::process::Command::new(&debugger_executable_path);
+            debugger_command
+                .stderr(std::process::Stdio::from(debugger_stderr_file))
+                #debugger_args
+                #debugger_env;
+            let debugger_stdout = match debugger_test_parser::run_session(#debugger_name.parse()?, debugger_command, debugger_script_contents, &expected_statements, timeout) {
+                Ok(debugger_stdout) => debugger_stdout,
+                Err(error) => {
+                    let mut debugger_stderr = String::new();
+                    let mut debugger_stderr_file = std::fs::File::open(&debugger_stderr_path)?;
+                    debugger_stderr_file.read_to_string(&mut debugger_stderr)?;
+                    return Err(std::boxed::Box::from(format!("Failed to run {} `{}`: {}\n{}\n{}", #debugger_name, debugger_executable_path.display(), error, debugger_stderr, #launch_help)));
+                }
+            };
+
+            // Keep the debugger output in the output file, the same as when the debugger runs the whole script.
+            let mut debugger_stdout_file = debugger_stdout_file;
+            debugger_stdout_file.write_all(debugger_stdout.as_bytes())?;
+            println!("Debugger stdout:\n{}\n", &debugger_stdout);
+        ),
+        _ => quote!(
+            // Start the debugger and run the debugger commands.
+            #run_debugger
+
+            // Wait for the debugger to detach from the test process and exit.
             // If debugger has not already quit, force quit the debugger.
             let mut debugger_stdout = String::new();
-            match child.try_wait()? {
+            match debugger_test_parser::wait_for_debugger_exit(&mut child, timeout)? {
                 Some(status) => {
                     // Bail early if the debugger process didn't execute successfully.
                     let mut debugger_stdout_file = std::fs::File::open(&debugger_stdout_path)?;
@@ -221,31 +1450,834 @@ pub fn debugger_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                     // Force kill the debugger process if it has not exited yet.
                     println!("killing debugger process.");
                     child.kill().expect("debugger has been running for too long");
+                    child.wait()?;
 
                     let mut debugger_stdout_file = std::fs::File::open(&debugger_stdout_path)?;
                     debugger_stdout_file.read_to_string(&mut debugger_stdout)?;
                     println!("Debugger stdout:\n{}\n", &debugger_stdout);
                 }
             }
+        ),
+    };
 
-            // Verify the expected contents of the debugger output.
-            let expected_statements = vec![#(#expected_statements),*];
-            debugger_test_parser::parse(debugger_stdout, expected_statements)?;
+    let check_launch_status = match mode {
+        DebuggerMode::Attach => quote!(),
+        DebuggerMode::Launch | DebuggerMode::Session => {
+            quote!(
+                // Verify the test function completed in the launched test executable.
+                if !launch_status_path.exists() {
+                    return Err(std::boxed::Box::from(format!("The test function did not complete when launched under the debugger.\n{}\n", debugger_stdout)));
+                }
+            )
+        }
+    };
+
+    // When launched under the debugger, the test executable runs the test function and exits.
+    let launched_test = match mode {
+        DebuggerMode::Attach => quote!(),
+        DebuggerMode::Launch | DebuggerMode::Session => quote!(
+            const LAUNCH_STATUS_ENV_VAR: &str = "DEBUGGER_TEST_LAUNCH_STATUS";
+            if let Some(launch_status_path) = std::env::var_os(LAUNCH_STATUS_ENV_VAR) {
+                // Wait for the debugger to attach when the test executable was launched for a debug adapter.
+                debugger_test_parser::wait_for_debugger(std::time::Duration::from_secs(#timeout))?;
+                #fn_ident();
+                std::fs::write(launch_status_path, "ok")?;
+                return Ok(());
+            }
+        ),
+    };
+
+    // Verify the expected statements of each breakpoint label in the output of the commands run for the label.
+    let verified_labels = break_labels
+        .iter()
+        .filter(|break_label| !break_label.expected_statements.is_empty())
+        .collect::<Vec<&BreakLabel>>();
+    let label_names = verified_labels.iter().map(|break_label| &break_label.label);
+    let label_expected_statements = verified_labels
+        .iter()
+        .map(|break_label| &break_label.expected_statements);
+    let verify_break_labels = quote!(
+        #(debugger_test_parser::parse_break_label(&debugger_stdout, #label_names, vec![#(#label_expected_statements),*])?;)*
+    );
+
+    // Verify the expected statements of the setup in the output of the setup commands.
+    let verify_setup = match section.expected_setup.is_empty() {
+        true => quote!(),
+        false => {
+            let expected_setup = &section.expected_setup;
+            quote!(debugger_test_parser::parse_setup(&debugger_stdout, vec![#(#expected_setup),*])?;)
+        }
+    };
 
-            #[cfg(windows)]
-            unsafe {
-                while IsDebuggerPresent() == 1 {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
+    // Fail the test before the debugger is launched when the visualizer file does not exist.
+    let (check_visualizer, debugger_script_contents) = match (
+        &section.visualizer,
+        script_options.visualizer,
+    ) {
+        (Some(visualizer), Some(script_visualizer)) => {
+            // Only the lines which load the visualizer refer to the manifest directory, any other command is left as is.
+            // The visualizer is loaded before the setup and test commands, so it is the first occurrence of these lines.
+            let load_visualizer = ScriptCommand::LoadVisualizer(script_visualizer.to_string());
+            let load_visualizer_lines = match debugger_type {
+                DebuggerType::Cdb | DebuggerType::Mock => CdbRenderer.render(&load_visualizer),
+                DebuggerType::Gdb | DebuggerType::RustGdb => GdbRenderer.render(&load_visualizer),
+                DebuggerType::Lldb | DebuggerType::RustLldb => {
+                    LldbRenderer.render(&load_visualizer)
                 }
+                _ => vec![],
             }
+            .join("\n");
+            (
+                quote!(
+                    let visualizer_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(#visualizer);
+                    if !visualizer_path.is_file() {
+                        return Err(format!("The visualizer file `{}` does not exist.", visualizer_path.display()).into());
+                    }
+                ),
+                quote!(&#debugger_script_contents.replacen(
+                    #load_visualizer_lines,
+                    &#load_visualizer_lines.replace(#MANIFEST_DIR_PLACEHOLDER, env!("CARGO_MANIFEST_DIR")),
+                    1,
+                )),
+            )
+        }
+        _ => (quote!(), quote!(#debugger_script_contents)),
+    };
+
+    // Include the files of `commands_file` and `expected_file`, so the test is rebuilt when one of them changes.
+    let tracked_files = &section.tracked_files;
+
+    // Create the test function that will launch the debugger and run debugger commands.
+    quote!(
+        #[test]
+        #cfg_attr
+        fn #test_fn_ident() -> std::result::Result<(), Box<dyn std::error::Error>> {
+            use std::io::Read;
+            use std::io::Write;
+
+            #(const _: &str = include_str!(#tracked_files);)*
+
+            #launched_test
+
+            #check_visualizer
+
+            let debugger_script_contents = #debugger_script_contents;
+            let expected_statements = vec![#(#expected_statements),*];
+
+            // When replaying recorded debugger output, verify the recorded output instead of launching the debugger.
+            let transcript_name = format!("{}::{}", module_path!(), #test_fn_name);
+            if let Some(debugger_stdout) = debugger_test_parser::replay_transcript(&transcript_name, debugger_script_contents)? {
+                #verify_setup
+                #verify_break_labels
+                debugger_test_parser::parse(debugger_stdout, expected_statements)?;
+                return Ok(());
+            }
+
+            #debugger_attach_guard
+
+            let current_exe_filename = std::env::current_exe()?.file_stem().expect("must have a valid file name").to_string_lossy().to_string();
+
+            // Create a temporary file to store the debugger script to run.
+            let debugger_script_filename = format!("{}_{}.debugger_script", current_exe_filename, #test_fn_name);
+            let debugger_script_path = std::env::temp_dir().join(debugger_script_filename);
+
+            // Write the contents of the debugger script to a new file.
+            let mut debugger_script = std::fs::File::create(&debugger_script_path)?;
+            writeln!(debugger_script, "{}", debugger_script_contents)?;
+
+            // Create a temporary file to store the stdout and stderr from the debugger output.
+            let debugger_stdout_path = debugger_script_path.with_extension("debugger_out");
+            let debugger_stderr_path = debugger_script_path.with_extension("debugger_err");
+
+            let debugger_stdout_file = std::fs::File::create(&debugger_stdout_path)?;
+            let debugger_stderr_file = std::fs::File::create(&debugger_stderr_path)?;
+
+            // Resolve the debugger executable when the test runs so the environment
+            // of the test run decides which debugger binary is launched.
+            let debugger_executable_path = #debugger_executable_path;
+            let timeout = std::time::Duration::from_secs(#timeout);
+
+            #collect_debugger_output
+
+            // Save the debugger script and output when recording debugger output.
+            debugger_test_parser::record_transcript(&transcript_name, debugger_script_contents, &debugger_stdout)?;
 
-            #[cfg(not(windows))]
-            std::thread::sleep(std::time::Duration::from_secs(3));
+            #check_launch_status
+
+            // Verify the expected contents of the debugger output.
+            #verify_setup
+            #verify_break_labels
+            debugger_test_parser::parse(debugger_stdout, expected_statements)?;
 
             Ok(())
         }
-    ));
+    )
+}
+
+#[cfg(test)]
+fn debugger_types(invoc: &DebuggerTest) -> Vec<DebuggerType> {
+    invoc
+        .sections
+        .iter()
+        .map(|section| section.debugger_type)
+        .collect()
+}
+
+#[test]
+fn test_parse_debugger_test() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dv", expected_statements = "a = 0n5""#,
+    )
+    .unwrap();
+    assert_eq!(vec![DebuggerType::Cdb], debugger_types(&invoc));
+    assert_eq!("dv", invoc.sections[0].commands);
+    assert_eq!("a = 0n5", invoc.sections[0].expected_statements);
+    assert!(invoc.sections[0].debugger_path.is_none());
+    assert_eq!(DEFAULT_TIMEOUT_SECS, invoc.sections[0].timeout);
+    assert_eq!(DebuggerMode::Attach, invoc.sections[0].mode);
+}
+
+#[test]
+fn test_parse_debugger_test_fixture() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "mock", commands = "", expected_statements = "", fixture = "tests/fixtures/test.txt""#,
+    )
+    .unwrap();
+    assert_eq!(DebuggerType::Mock, invoc.sections[0].debugger_type);
+    assert_eq!(
+        Some("tests/fixtures/test.txt"),
+        invoc.sections[0].fixture.as_deref()
+    );
+
+    let gdb_fixture = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", fixture = "tests/fixtures/test.txt""#,
+    );
+    assert_eq!(
+        "`fixture` can only be used with the `mock` debugger",
+        gdb_fixture.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_breakpoints() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = ["src/foo.rs:42", "my_crate::parse"]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            BreakpointLocation::Line {
+                file: "src/foo.rs".to_string(),
+                line: 42
+            },
+            BreakpointLocation::Function("my_crate::parse".to_string()),
+        ],
+        invoc.sections[0]
+            .breakpoints
+            .iter()
+            .map(|breakpoint| breakpoint.location.clone())
+            .collect::<Vec<BreakpointLocation>>()
+    );
+
+    // A location is never split, so a file name can contain a comma.
+    let comma_file = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/a,b.rs:3""#,
+    )
+    .unwrap();
+    assert_eq!(
+        BreakpointLocation::Line {
+            file: "src/a,b.rs".to_string(),
+            line: 3
+        },
+        comma_file.sections[0].breakpoints[0].location
+    );
+
+    let empty_breakpoint = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = ["src/foo.rs:42", ""]"#,
+    );
+    assert_eq!(
+        "Invalid breakpoint location: ``.",
+        empty_breakpoint.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_breakpoint_options() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = ["src/foo.rs:42", "my_crate::parse"], condition("src/foo.rs:42") = "f(a, b) > 0", hit_count("my_crate::parse") = 1000"#,
+    )
+    .unwrap();
+    assert_eq!(
+        Some(String::from("f(a, b) > 0")),
+        invoc.sections[0].breakpoints[0].condition
+    );
+    assert_eq!(None, invoc.sections[0].breakpoints[0].hit_count);
+    assert_eq!(None, invoc.sections[0].breakpoints[1].condition);
+    assert_eq!(Some(1000), invoc.sections[0].breakpoints[1].hit_count);
+
+    let unknown_breakpoint = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", condition("src/foo.rs:43") = "i == 42""#,
+    );
+    assert_eq!(
+        "`src/foo.rs:43` is not one of the locations of `breakpoints`",
+        unknown_breakpoint.err().unwrap().to_string()
+    );
+
+    let zero_hit_count = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", hit_count("src/foo.rs:42") = 0"#,
+    );
+    assert_eq!(
+        "`hit_count` is the number of the first hit to stop at and must be at least 1",
+        zero_hit_count.err().unwrap().to_string()
+    );
+
+    let duplicate_condition = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", condition("src/foo.rs:42") = "i == 42", condition("src/foo.rs:42") = "i == 43""#,
+    );
+    assert_eq!(
+        "`condition(\"src/foo.rs:42\")` is specified more than once.",
+        duplicate_condition.err().unwrap().to_string()
+    );
+
+    let duplicate_hit_count = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", hit_count("src/foo.rs:42") = 2, hit_count("src/foo.rs:42") = 3"#,
+    );
+    assert_eq!(
+        "`hit_count(\"src/foo.rs:42\")` is specified more than once.",
+        duplicate_hit_count.err().unwrap().to_string()
+    );
+
+    let unsupported_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "lldb-dap", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", hit_count("src/foo.rs:42") = 2"#,
+    );
+    assert_eq!(
+        "debugger `lldb-dap` does not support the `condition` and `hit_count` options",
+        unsupported_debugger.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_break_labels() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", on_break("after_push") = "dx v", expected_on_break("after_push") = "v : { len=1 }", gdb(on_break("after_pop") = "print v")"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![BreakLabel {
+            label: "after_push".to_string(),
+            commands: vec!["dx v".to_string()],
+            expected_statements: vec!["v : { len=1 }".to_string()],
+        }],
+        invoc.sections[0].break_labels
+    );
+    assert_eq!("", invoc.sections[0].commands);
+    assert_eq!("after_pop", invoc.sections[1].break_labels[0].label);
+
+    let invalid_label =
+        syn::parse_str::<DebuggerTest>(r#"debugger = "cdb", on_break("after push") = "dx v""#);
+    assert_eq!(
+        "Invalid breakpoint label: `after push`, expected only letters, digits and underscores.",
+        invalid_label.err().unwrap().to_string()
+    );
+
+    let duplicate_label = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", on_break("after_push") = "dx v", on_break("after_push") = "dv""#,
+    );
+    assert_eq!(
+        "`on_break(\"after_push\")` is specified more than once.",
+        duplicate_label.err().unwrap().to_string()
+    );
+
+    let missing_commands = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", expected_on_break("after_push") = "v : { len=1 }""#,
+    );
+    assert_eq!(
+        "`expected_on_break(\"after_push\")` can only be used with `on_break(\"after_push\")`",
+        missing_commands.err().unwrap().to_string()
+    );
+
+    let unsupported_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb-mi", on_break("after_push") = "print v""#,
+    );
+    assert_eq!(
+        "debugger `gdb-mi` does not support the `on_break` option",
+        unsupported_debugger.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_capture() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, lldb", commands = "", expected_statements = "json:locals.a = 10", capture = "json", capture_expressions = ["a + 1", "f(a, 2)"]"#,
+    )
+    .unwrap();
+    assert_eq!(Some(CaptureMode::Json), invoc.sections[0].capture);
+    assert_eq!(Some(CaptureMode::Json), invoc.sections[1].capture);
+    assert_eq!(
+        vec!["a + 1".to_string(), "f(a, 2)".to_string()],
+        invoc.sections[1].capture_expressions
+    );
+
+    let cdb_capture = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", capture = "json""#,
+    );
+    assert_eq!(
+        "debugger `cdb` does not support the `capture` option",
+        cdb_capture.err().unwrap().to_string()
+    );
+
+    let invalid_capture = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", capture = "xml""#,
+    );
+    assert_eq!(
+        "Invalid capture option: `xml`.",
+        invalid_capture.err().unwrap().to_string()
+    );
+
+    let expressions_without_capture = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", capture_expressions = "a""#,
+    );
+    assert_eq!(
+        "`capture_expressions` can only be used with `capture = \"json\"`",
+        expressions_without_capture.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_watch() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, lldb", commands = "", expected_statements = "watch:self.head = 0 -> 1", watch = "self.head""#,
+    )
+    .unwrap();
+    assert_eq!(vec!["self.head".to_string()], invoc.sections[1].watch);
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", watch = ["self.head", "self.items[0]"]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec!["self.head".to_string(), "self.items[0]".to_string()],
+        invoc.sections[0].watch
+    );
+
+    let unsupported_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", watch = "self.head""#,
+    );
+    assert_eq!(
+        "debugger `cdb` does not support the `watch` option",
+        unsupported_debugger.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_files() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands_file = "tests/dbg/mock_commands.cmds", expected_file = "tests/dbg/mock_commands.expected""#,
+    )
+    .unwrap();
+    assert_eq!("dx a\n", invoc.sections[0].commands);
+    assert_eq!(
+        "a                : 1 [Type: int]\n",
+        invoc.sections[0].expected_statements
+    );
+    assert_eq!(2, invoc.sections[0].tracked_files.len());
+    assert!(invoc.sections[0].tracked_files[0].ends_with("mock_commands.cmds"));
+
+    let both_commands = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dx a", commands_file = "tests/dbg/mock_commands.cmds", expected_statements = """#,
+    );
+    assert_eq!(
+        "`commands` and `commands_file` cannot both be specified",
+        both_commands.err().unwrap().to_string()
+    );
+
+    let repeated_file = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands_file = "tests/dbg/mock_commands.cmds", commands_file = "tests/dbg/mock_commands.cmds", expected_statements = """#,
+    );
+    assert_eq!(
+        "`commands_file` is specified more than once.",
+        repeated_file.err().unwrap().to_string()
+    );
+
+    let missing_file = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_file = "tests/dbg/missing.expected""#,
+    );
+    assert!(missing_file
+        .err()
+        .unwrap()
+        .to_string()
+        .starts_with("Failed to read `"));
+}
+
+#[test]
+fn test_parse_debugger_test_setup() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", setup = ".nvload a.natvis\n.sympath+ C:\\symbols", expected_setup = "pattern:Successfully loaded .*a.natvis""#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![".nvload a.natvis", ".sympath+ C:\\symbols"],
+        invoc.sections[0].setup
+    );
+    assert_eq!(1, invoc.sections[0].expected_setup.len());
+
+    let missing_setup = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", expected_setup = "a""#,
+    );
+    assert_eq!(
+        "`expected_setup` can only be used with `setup`",
+        missing_setup.err().unwrap().to_string()
+    );
+
+    let unsupported_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb-mi", commands = "", expected_statements = "", setup = "source printers.py""#,
+    );
+    assert_eq!(
+        "debugger `gdb-mi` does not support the `setup` option",
+        unsupported_debugger.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_visualizers() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"cdb(commands = "", expected_statements = "", natvis = "tests/vec.natvis"), gdb(commands = "", expected_statements = "", gdb_pretty_printer = "tests/vec_printer.py"), lldb(commands = "", expected_statements = "", lldb_formatter = "tests/vec_formatter.py")"#,
+    )
+    .unwrap();
+    let visualizers = invoc
+        .sections
+        .iter()
+        .map(|section| section.visualizer.clone().unwrap())
+        .collect::<Vec<String>>();
+    assert_eq!("tests/vec.natvis", visualizers[0]);
+    assert_eq!("tests/vec_printer.py", visualizers[1]);
+    assert_eq!("tests/vec_formatter.py", visualizers[2]);
+
+    let unsupported_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb-mi", commands = "", expected_statements = "", gdb_pretty_printer = "tests/vec_printer.py""#,
+    );
+    assert_eq!(
+        "debugger `gdb-mi` does not support the `gdb_pretty_printer` option",
+        unsupported_debugger.err().unwrap().to_string()
+    );
+
+    let other_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb, gdb", commands = "", expected_statements = "", natvis = "tests/vec.natvis""#,
+    );
+    assert_eq!(
+        "debugger `gdb` does not support the `natvis` option",
+        other_debugger.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_gdb_mi() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb-mi", commands = "", expected_statements = """#,
+    )
+    .unwrap();
+    assert_eq!(DebuggerType::GdbMi, invoc.sections[0].debugger_type);
+    assert_eq!(DebuggerMode::Launch, invoc.sections[0].mode);
+
+    let attach_mode = syn::parse_str::<DebuggerTest>(
+        r#"gdb_mi(commands = "", expected_statements = "", mode = "attach")"#,
+    );
+    assert_eq!(
+        "debugger `gdb-mi` only supports `mode = \"launch\"`",
+        attach_mode.err().unwrap().to_string()
+    );
+
+    let session_mode = syn::parse_str::<DebuggerTest>(
+        r#"gdb_mi(commands = "", expected_statements = "", mode = "session")"#,
+    );
+    assert_eq!(
+        "debugger `gdb-mi` only supports `mode = \"launch\"`",
+        session_mode.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_dap() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "lldb-dap, gdb-dap", commands = "variables", expected_statements = "a = 10""#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::LldbDap, DebuggerType::GdbDap],
+        debugger_types(&invoc)
+    );
+    assert_eq!(DebuggerMode::Launch, invoc.sections[0].mode);
+
+    let launch_mode = syn::parse_str::<DebuggerTest>(
+        r#"lldb_dap(commands = "", expected_statements = "", mode = "launch")"#,
+    );
+    assert_eq!(
+        "debugger `lldb-dap` does not support the `mode` option",
+        launch_mode.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_mode() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", mode = "launch", lldb(commands = "", expected_statements = "", mode = "attach")"#,
+    )
+    .unwrap();
+    assert_eq!(DebuggerMode::Launch, invoc.sections[0].mode);
+    assert_eq!(DebuggerMode::Attach, invoc.sections[1].mode);
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", mode = "session""#,
+    )
+    .unwrap();
+    assert_eq!(DebuggerMode::Session, invoc.sections[0].mode);
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dx ${ptr}", expected_statements = "pattern:(?P<ptr>0x[0-9a-f]+)", mode = "session""#,
+    )
+    .unwrap();
+    assert_eq!(DebuggerMode::Session, invoc.sections[0].mode);
+
+    let captures_without_session = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dx ${ptr}", expected_statements = "pattern:(?P<ptr>0x[0-9a-f]+)", mode = "launch""#,
+    );
+    assert_eq!(
+        "`${ptr}` can only be used in `commands` with `mode = \"session\"`",
+        captures_without_session.err().unwrap().to_string()
+    );
+
+    let invalid_mode = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", mode = "run""#,
+    );
+    assert_eq!(
+        "Invalid debugger mode option: `run`.",
+        invalid_mode.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_timeout() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", timeout = 10, lldb(commands = "", expected_statements = "", timeout = 20)"#,
+    )
+    .unwrap();
+    assert_eq!(10, invoc.sections[0].timeout);
+    assert_eq!(20, invoc.sections[1].timeout);
+
+    let invalid_timeout = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", timeout = "10""#,
+    );
+    assert_eq!(
+        "Expected a literal integer for the value of `timeout`",
+        invalid_timeout.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_debugger_path() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", debugger_path = "$GDB_HOME/bin/gdb""#,
+    )
+    .unwrap();
+    assert_eq!(vec![DebuggerType::Gdb], debugger_types(&invoc));
+    assert_eq!(
+        Some(String::from("$GDB_HOME/bin/gdb")),
+        invoc.sections[0].debugger_path
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_errors() {
+    let missing_commands =
+        syn::parse_str::<DebuggerTest>(r#"debugger = "cdb", expected_statements = """#);
+    assert_eq!(
+        "Expected value `commands`",
+        missing_commands.err().unwrap().to_string()
+    );
+
+    let invalid_debugger_path = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", debugger_path = 5"#,
+    );
+    assert_eq!(
+        "Expected a literal string for the value of `debugger_path`",
+        invalid_debugger_path.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_duplicates() {
+    let duplicate_commands = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dx a", commands = "dx b", expected_statements = """#,
+    );
+    assert_eq!(
+        "`commands` is specified more than once.",
+        duplicate_commands.err().unwrap().to_string()
+    );
+
+    let duplicate_expected_statements = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "a", expected_statements = "b""#,
+    );
+    assert_eq!(
+        "`expected_statements` is specified more than once.",
+        duplicate_expected_statements.err().unwrap().to_string()
+    );
+
+    let duplicate_mode = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", mode = "launch", mode = "attach""#,
+    );
+    assert_eq!(
+        "`mode` is specified more than once.",
+        duplicate_mode.err().unwrap().to_string()
+    );
+
+    let duplicate_timeout = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "", expected_statements = "", timeout = 10, timeout = 20"#,
+    );
+    assert_eq!(
+        "`timeout` is specified more than once.",
+        duplicate_timeout.err().unwrap().to_string()
+    );
 
-    debugger_test_fn.extend(proc_macro::TokenStream::from(item.to_token_stream()).into_iter());
-    debugger_test_fn
+    let duplicate_breakpoints = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", breakpoints = "src/foo.rs:42", breakpoints = ["my_crate::parse"]"#,
+    );
+    assert_eq!(
+        "`breakpoints` is specified more than once.",
+        duplicate_breakpoints.err().unwrap().to_string()
+    );
+
+    let duplicate_in_group = syn::parse_str::<DebuggerTest>(
+        r#"gdb(commands = "", expected_statements = "", setup = "a", setup = "b")"#,
+    );
+    assert_eq!(
+        "`setup` is specified more than once.",
+        duplicate_in_group.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_multiple_debuggers() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, lldb,cdb", commands = "", expected_statements = """#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Gdb, DebuggerType::Lldb, DebuggerType::Cdb],
+        debugger_types(&invoc)
+    );
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", debugger = "rust-gdb", commands = "", expected_statements = """#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Gdb, DebuggerType::RustGdb],
+        debugger_types(&invoc)
+    );
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, lldb", commands = "", expected_statements = """#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Gdb, DebuggerType::Lldb],
+        debugger_types(&invoc)
+    );
+
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = ["gdb", "lldb"], commands = "", expected_statements = """#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Gdb, DebuggerType::Lldb],
+        debugger_types(&invoc)
+    );
+
+    let duplicate_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, GDB", commands = "", expected_statements = """#,
+    );
+    assert_eq!(
+        "debugger `gdb` is specified more than once.",
+        duplicate_debugger.err().unwrap().to_string()
+    );
+
+    let invalid_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, windbg", commands = "", expected_statements = """#,
+    );
+    assert_eq!(
+        "debugger `windbg` must be a valid debugger option.",
+        invalid_debugger.err().unwrap().to_string()
+    );
+
+    let debugger_path = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb, lldb", commands = "", expected_statements = "", debugger_path = "gdb""#,
+    );
+    assert_eq!(
+        "`debugger_path` can only be used with a single debugger",
+        debugger_path.err().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_parse_debugger_test_debugger_groups() {
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"
+        cdb(commands = "dx a", expected_statements = "a : 5 [Type: int]"),
+        gdb(commands = "print a", expected_statements = "$1 = 5", debugger_path = "/opt/gdb"),
+        rust_lldb(commands = "frame variable a", expected_statements = "(int) a = 5")"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Cdb, DebuggerType::Gdb, DebuggerType::RustLldb],
+        debugger_types(&invoc)
+    );
+    assert_eq!("dx a", invoc.sections[0].commands);
+    assert_eq!("a : 5 [Type: int]", invoc.sections[0].expected_statements);
+    assert_eq!("print a", invoc.sections[1].commands);
+    assert_eq!(
+        Some(String::from("/opt/gdb")),
+        invoc.sections[1].debugger_path
+    );
+    assert_eq!("(int) a = 5", invoc.sections[2].expected_statements);
+
+    // Top level meta items and debugger groups can be combined.
+    let invoc = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "cdb", commands = "dv", expected_statements = "", gdb(commands = "info locals", expected_statements = "")"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![DebuggerType::Cdb, DebuggerType::Gdb],
+        debugger_types(&invoc)
+    );
+    assert_eq!("info locals", invoc.sections[1].commands);
+
+    let duplicate_debugger = syn::parse_str::<DebuggerTest>(
+        r#"debugger = "gdb", commands = "", expected_statements = "", gdb(commands = "", expected_statements = "")"#,
+    );
+    assert_eq!(
+        "debugger `gdb` is specified more than once.",
+        duplicate_debugger.err().unwrap().to_string()
+    );
+
+    let missing_expected_statements =
+        syn::parse_str::<DebuggerTest>(r#"lldb(commands = "frame variable")"#);
+    assert_eq!(
+        "Expected value `expected_statements`",
+        missing_expected_statements.err().unwrap().to_string()
+    );
+
+    let missing_debugger = syn::parse_str::<DebuggerTest>(
+        r#"commands = "", expected_statements = "", gdb(commands = "", expected_statements = "")"#,
+    );
+    assert_eq!(
+        "Expected value `debugger`",
+        missing_debugger.err().unwrap().to_string()
+    );
+
+    let invalid_debugger =
+        syn::parse_str::<DebuggerTest>(r#"windbg(commands = "", expected_statements = "")"#);
+    assert_eq!(
+        "debugger `windbg` must be a valid debugger option.",
+        invalid_debugger.err().unwrap().to_string()
+    );
 }
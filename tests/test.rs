@@ -1,4 +1,4 @@
-use debugger_test::debugger_test;
+use debugger_test::{break_here, debugger_test};
 
 #[inline(never)]
 fn __break() {}
@@ -47,3 +47,468 @@ fn test_commands_with_expectations() {
     assert_eq!(b, 10);
     __break();
 }
+
+#[debugger_test(cdb(
+    commands = r#"
+dx a
+g
+dx a"#,
+    expected_statements = r#"
+a = 0n1
+a = 0n2"#
+))]
+fn test_debugger_group() {
+    let mut a = 1;
+    __break();
+
+    a += 1;
+    assert_eq!(a, 2);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "cdb",
+    commands = "dx a",
+    expected_statements = "a = 0n7",
+    mode = "launch"
+)]
+fn test_launch_mode() {
+    let a = 7;
+    __break();
+    assert_eq!(a, 7);
+}
+
+#[debugger_test(
+    debugger = "cdb",
+    on_break("after_push") = "dx v.len()",
+    expected_on_break("after_push") = r#"
+v.len() : 0x1
+v.len() : 0x2
+v.len() : 0x3"#,
+    on_break("after_clear") = "dx v.len()",
+    expected_on_break("after_clear") = "v.len() : 0x0"
+)]
+fn test_break_labels() {
+    let mut v = Vec::new();
+    for i in 0..3 {
+        v.push(i);
+        break_here!("after_push");
+    }
+
+    v.clear();
+    break_here!("after_clear");
+    assert!(v.is_empty());
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    on_break("after_add") = "print total",
+    expected_on_break("after_add") = r#"
+pattern:\$\d+ = 1
+pattern:\$\d+ = 3
+pattern:\$\d+ = 6"#
+)]
+fn test_gdb_break_labels() {
+    let mut total = 0;
+    for i in 1..4 {
+        total += i;
+        break_here!("after_add");
+    }
+    assert_eq!(total, 6);
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    on_break("after_add") = "frame variable total",
+    expected_on_break("after_add") = r#"
+total = 1
+total = 3
+total = 6"#
+)]
+fn test_lldb_break_labels() {
+    let mut total = 0;
+    for i in 1..4 {
+        total += i;
+        break_here!("after_add");
+    }
+    assert_eq!(total, 6);
+}
+
+#[inline(never)]
+fn gdb_add_item(total: u32, i: u32) -> u32 {
+    total + i
+}
+
+#[inline(never)]
+fn gdb_add_square(total: u32, i: u32) -> u32 {
+    total + i * i
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    commands = r#"
+print i
+continue
+print i"#,
+    expected_statements = r#"
+pattern:\$\d+ = 2
+pattern:\$\d+ = 3"#,
+    breakpoints = ["test::gdb_add_item", "test::gdb_add_square"],
+    condition("test::gdb_add_item") = "i == 2",
+    hit_count("test::gdb_add_square") = 3
+)]
+fn test_gdb_breakpoint_options() {
+    let mut total = 0;
+    for i in 1..4 {
+        total = gdb_add_item(total, i);
+        total = gdb_add_square(total, i);
+    }
+    assert_eq!(total, 20);
+}
+
+#[inline(never)]
+fn lldb_add_item(total: u32, i: u32) -> u32 {
+    total + i
+}
+
+#[inline(never)]
+fn lldb_add_square(total: u32, i: u32) -> u32 {
+    total + i * i
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    commands = r#"
+frame variable i
+continue
+frame variable i"#,
+    expected_statements = r#"
+i = 2
+i = 3"#,
+    breakpoints = ["test::lldb_add_item", "test::lldb_add_square"],
+    condition("test::lldb_add_item") = "i == 2",
+    hit_count("test::lldb_add_square") = 3
+)]
+fn test_lldb_breakpoint_options() {
+    let mut total = 0;
+    for i in 1..4 {
+        total = lldb_add_item(total, i);
+        total = lldb_add_square(total, i);
+    }
+    assert_eq!(total, 20);
+}
+
+#[inline(never)]
+fn increment(count: &mut u32) {
+    *count += 1;
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    commands = "",
+    expected_statements = r#"
+watch:count = 0 -> 1
+watch:count = 1 -> 2"#,
+    watch = "count"
+)]
+fn test_gdb_watch() {
+    let mut count = 0;
+    __break();
+    increment(&mut count);
+    increment(&mut count);
+    assert_eq!(count, 2);
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    commands = "",
+    expected_statements = r#"
+watch:count = 0 -> 1
+watch:count = 1 -> 2"#,
+    watch = "count"
+)]
+fn test_lldb_watch() {
+    let mut count = 0;
+    __break();
+    increment(&mut count);
+    increment(&mut count);
+    assert_eq!(count, 2);
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    setup = r#"
+set print pretty on
+show print pretty"#,
+    expected_setup = "Pretty formatting of structures is on.",
+    commands = "print a",
+    expected_statements = r#"pattern:\$\d+ = 7"#
+)]
+fn test_gdb_setup() {
+    let a = 7;
+    __break();
+    assert_eq!(a, 7);
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    setup = r#"
+settings set target.max-children-count 5
+settings show target.max-children-count"#,
+    expected_setup = "target.max-children-count (unsigned) = 5",
+    commands = "frame variable a",
+    expected_statements = "a = 7"
+)]
+fn test_lldb_setup() {
+    let a = 7;
+    __break();
+    assert_eq!(a, 7);
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    commands = r#"
+print a
+continue
+print a
+continue
+print b
+continue
+print b"#,
+    expected_statements = r#"
+pattern:\$\d+ = 0
+pattern:\$\d+ = 5
+pattern:\$\d+ = 25
+pattern:\$\d+ = 10"#
+)]
+fn test_gdb_commands_with_expectations() {
+    let mut a = 0;
+    __break();
+
+    a += 5;
+    assert_eq!(a, 5);
+    __break();
+
+    let mut b = 25;
+    __break();
+
+    b -= 15;
+    assert_eq!(b, 10);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "gdb",
+    commands = "print a",
+    expected_statements = r#"pattern:\$\d+ = 7"#,
+    mode = "launch"
+)]
+fn test_gdb_launch_mode() {
+    let a = 7;
+    __break();
+    assert_eq!(a, 7);
+}
+
+#[debugger_test(
+    debugger = "gdb-mi",
+    commands = r#"
+-data-evaluate-expression a
+-exec-continue
+print a"#,
+    expected_statements = r#"
+value = 7
+stopped.reason = breakpoint-hit
+pattern:\$\d+ = 12"#
+)]
+fn test_gdb_mi_commands_with_expectations() {
+    let mut a = 7;
+    __break();
+
+    a += 5;
+    assert_eq!(a, 12);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    commands = r#"
+frame variable a
+continue
+frame variable a
+continue
+frame variable b
+continue
+frame variable b"#,
+    expected_statements = r#"
+a = 0
+a = 5
+b = 25
+b = 10"#
+)]
+fn test_lldb_commands_with_expectations() {
+    let mut a = 0;
+    __break();
+
+    a += 5;
+    assert_eq!(a, 5);
+    __break();
+
+    let mut b = 25;
+    __break();
+
+    b -= 15;
+    assert_eq!(b, 10);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "lldb",
+    commands = "frame variable a",
+    expected_statements = "a = 7",
+    mode = "launch"
+)]
+fn test_lldb_launch_mode() {
+    let a = 7;
+    __break();
+    assert_eq!(a, 7);
+}
+
+#[debugger_test(
+    debugger = "lldb-dap",
+    commands = r#"
+variables
+continue
+variables"#,
+    expected_statements = r#"
+scope = Locals
+a = 7
+a = 12"#
+)]
+fn test_lldb_dap_commands_with_expectations() {
+    let mut a = 7;
+    __break();
+
+    a += 5;
+    assert_eq!(a, 12);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands = r#"
+dx a
+g
+dx a
+g
+dv"#,
+    expected_statements = r#"
+a                : 1 [Type: int]
+a                : 2 [Type: int]
+pattern:a = 0n\d+
+b = 0n10"#,
+    fixture = "tests/fixtures/mock_commands_with_expectations.txt"
+)]
+fn test_mock_commands_with_expectations() {
+    let mut a = 1;
+    __break();
+
+    a += 1;
+    assert_eq!(a, 2);
+    __break();
+
+    let b = a * 5;
+    assert_eq!(b, 10);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands = "dx a",
+    expected_statements = "a                : 1 [Type: int]",
+    mode = "launch",
+    fixture = "tests/fixtures/mock_commands_with_expectations.txt"
+)]
+fn test_mock_launch_mode() {
+    let a = 1;
+    __break();
+    assert_eq!(a, 1);
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands_file = "tests/dbg/mock_commands.cmds",
+    expected_file = "tests/dbg/mock_commands.expected",
+    mode = "launch",
+    fixture = "tests/fixtures/mock_commands_with_expectations.txt"
+)]
+fn test_mock_commands_file() {
+    let a = 1;
+    __break();
+    assert_eq!(a, 1);
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands = "dx a",
+    expected_statements = "a                : 1 [Type: int]",
+    natvis = "tests/dbg/mock.natvis",
+    mode = "launch",
+    fixture = "tests/fixtures/mock_commands_with_expectations.txt"
+)]
+fn test_mock_natvis() {
+    let a = 1;
+    __break();
+    assert_eq!(a, 1);
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands = r#"
+dx a
+g
+dx a"#,
+    expected_statements = r#"
+a                : 1 [Type: int]
+a                : 2 [Type: int]"#,
+    mode = "session",
+    fixture = "tests/fixtures/mock_commands_with_expectations.txt"
+)]
+fn test_mock_session_mode() {
+    let mut a = 1;
+    __break();
+
+    a += 1;
+    assert_eq!(a, 2);
+    __break();
+}
+
+#[debugger_test(
+    debugger = "mock",
+    commands = r#"
+dx &a
+dx *(int*)${ptr}"#,
+    expected_statements = r#"
+pattern:&a\s+: (?P<ptr>0x[0-9a-f]+) \[Type: int \*\]
+pattern:\*\(int\*\)0x[0-9a-f]+ : 5 \[Type: int\]"#,
+    mode = "session",
+    fixture = "tests/fixtures/mock_session_captures.txt"
+)]
+fn test_mock_session_captures() {
+    let a = 5;
+    __break();
+    assert_eq!(a, 5);
+}
+
+// Two tests which attach a debugger to the test process at the same time take turns.
+#[test]
+#[cfg(target_os = "linux")]
+fn test_parallel_attach() {
+    let gdb = std::thread::spawn(|| {
+        test_gdb_commands_with_expectations__gdb().map_err(|error| error.to_string())
+    });
+    let lldb = std::thread::spawn(|| {
+        test_lldb_commands_with_expectations__lldb().map_err(|error| error.to_string())
+    });
+    gdb.join().unwrap().unwrap();
+    lldb.join().unwrap().unwrap();
+}